@@ -1,4 +1,18 @@
-use bevy::prelude::*;
+use std::fs;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use bevy::{
+    math::Affine3A,
+    prelude::*,
+    scene::SceneInstanceReady,
+    transform::TransformSystem,
+    utils::HashMap,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::loading_state::LoadingAssets;
+
+const MODEL_CATALOG_PATH: &str = "assets/json/model_catalog.json";
 
 #[derive(Resource ,Debug, Default)]
 pub struct SceneAssets {
@@ -8,23 +22,298 @@ pub struct SceneAssets {
     pub planet: Handle<Scene>
 }
 
+/// 自发光颜色为线性 RGB，乘以 `strength` 后写入材质
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct EmissiveJson {
+    pub color: [f32; 3],
+    #[serde(default = "default_emissive_strength")]
+    pub strength: f32,
+}
+fn default_emissive_strength() -> f32 {
+    1.0
+}
+impl EmissiveJson {
+    pub fn to_linear(self) -> LinearRgba {
+        let [red, green, blue] = self.color;
+        LinearRgba::rgb(red, green, blue) * self.strength
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct ModelCatalogJson {
+    models: Vec<ModelCatalogEntryJson>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct ModelCatalogEntryJson {
+    name: String,
+    /// glTF 文件路径，取其中第一个场景
+    path: String,
+    /// 可作为恒星的随机候选
+    #[serde(default)]
+    star: bool,
+    #[serde(default)]
+    emissive: Option<EmissiveJson>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CatalogModel {
+    pub name: String,
+    pub scene: Handle<Scene>,
+    pub star: bool,
+    pub emissive: Option<EmissiveJson>,
+}
+
+/// 逻辑名称到 glTF 场景的映射，读取自 model_catalog.json
+#[derive(Resource, Debug)]
+pub struct ModelCatalog {
+    pub models: Vec<CatalogModel>,
+}
+impl FromWorld for ModelCatalog {
+    fn from_world(world: &mut World) -> Self {
+        let catalog = fs::read_to_string(MODEL_CATALOG_PATH).map_err(|err| err.to_string())
+            .and_then(|catalog| serde_json::from_str::<ModelCatalogJson>(&catalog).map_err(|err| err.to_string()));
+        let mut loading = world.get_resource_or_insert_with(LoadingAssets::default);
+        let catalog = match catalog {
+            Ok(catalog) => catalog,
+            Err(err) => {
+                loading.fail(MODEL_CATALOG_PATH, err);
+                return Self { models: Vec::new() };
+            }
+        };
+        let asset_server = world.resource::<AssetServer>().clone();
+        let mut loading = world.get_resource_or_insert_with(LoadingAssets::default);
+        let models = catalog.models.into_iter().map(|model| CatalogModel {
+            scene: loading.load_scene(&asset_server, &model.path),
+            name: model.name,
+            star: model.star,
+            emissive: model.emissive,
+        }).collect();
+        Self { models }
+    }
+}
+impl ModelCatalog {
+    pub fn get(&self, name: &str) -> Option<&CatalogModel> {
+        self.models.iter().find(|model| model.name == name)
+    }
+
+    /// 未指定模型时按 `key` 的哈希在同类模型中挑选，同一个 key 总得到同一个模型
+    pub fn pick(&self, name: Option<&str>, key: &str, star: bool) -> Option<&CatalogModel> {
+        if let Some(name) = name {
+            match self.get(name) {
+                Some(model) => return Some(model),
+                None => warn!("model {} not found in {}, picking one at random", name, MODEL_CATALOG_PATH),
+            }
+        }
+        let candidates: Vec<&CatalogModel> = self.models.iter().filter(|model| model.star == star).collect();
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        candidates.get((hasher.finish() % candidates.len().max(1) as u64) as usize).copied()
+    }
+}
+
+/// 场景实例生成后，把其中所有标准材质替换为带该自发光颜色的副本
+#[derive(Component, Debug, Clone, Copy)]
+pub struct EmissiveOverride(pub LinearRgba);
+
+/// 场景中所有网格顶点的包围球，坐标为场景根节点的局部坐标
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelBounds {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+/// 已加载场景的包围球缓存
+#[derive(Resource, Debug, Default)]
+pub struct SceneBounds(pub HashMap<AssetId<Scene>, ModelBounds>);
+
+/// 场景加载完成后自动缩放，使模型的视觉半径等于给定值；缩放完成前模型不可见。
+/// 场景被移到一个子实体上，子实体平移 -center * scale，使包围球球心与实体原点重合
+#[derive(Component, Debug, Clone, Copy)]
+pub struct FitToRadius(pub f32);
+
+/// 承载缩放后场景的子实体
+#[derive(Component, Debug)]
+struct FittedModel;
+
 pub struct AssetLoaderPlugin;
 
 impl Plugin for AssetLoaderPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<SceneAssets>()
-            .add_systems(Startup, load_assets);
+            .init_resource::<SceneBounds>()
+            .init_resource::<ModelCatalog>()
+            .add_systems(Startup, load_assets)
+            .observe(detach_fitted_scene)
+            .add_systems(Update, apply_emissive_overrides.run_if(on_event::<SceneInstanceReady>()))
+            .add_systems(PostUpdate, (
+                invalidate_scene_bounds,
+                hide_unfitted_models,
+                fit_models_to_radius,
+            ).chain().before(TransformSystem::TransformPropagate));
     }
 }
 
 fn load_assets(
     mut scene_assets: ResMut<SceneAssets>,
+    mut loading: ResMut<LoadingAssets>,
     asset_server: Res<AssetServer>,
 ) {
     *scene_assets = SceneAssets {
-        asteroids: asset_server.load(GltfAssetLabel::Scene(0).from_asset("models/Planet.glb")),
-        spaceship: asset_server.load("models/Spaceship.glb#Scene0"),
-        missile: asset_server.load("models/Spaceship-u105mYHLHU.glb#Scene0"),
-        planet: asset_server.load("models/Planet-1.glb#Scene0"),
+        asteroids: loading.load_scene(&asset_server, "models/Planet.glb"),
+        spaceship: loading.load_scene(&asset_server, "models/Spaceship.glb"),
+        missile: loading.load_scene(&asset_server, "models/Spaceship-u105mYHLHU.glb"),
+        planet: loading.load_scene(&asset_server, "models/Planet-1.glb"),
     }
-}
\ No newline at end of file
+}
+
+fn apply_emissive_overrides(
+    mut ready_events: EventReader<SceneInstanceReady>,
+    overrides: Query<&EmissiveOverride>,
+    parents: Query<&Parent, With<FittedModel>>,
+    children: Query<&Children>,
+    mut material_handles: Query<&mut Handle<StandardMaterial>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for event in ready_events.read() {
+        // 缩放过的场景挂在子实体上，覆盖设置在其父实体上
+        let owner = parents.get(event.parent).map_or(event.parent, |parent| parent.get());
+        let Ok(EmissiveOverride(emissive)) = overrides.get(owner) else { continue; };
+        // 同一场景内共用的材质只复制一次
+        let mut replaced = HashMap::new();
+        for entity in children.iter_descendants(event.parent) {
+            let Ok(mut handle) = material_handles.get_mut(entity) else { continue; };
+            let new_handle = replaced.entry(handle.id()).or_insert_with(|| {
+                let mut material = materials.get(handle.id()).cloned().unwrap_or_default();
+                material.emissive = *emissive;
+                materials.add(material)
+            });
+            *handle = new_handle.clone();
+        }
+    }
+}
+
+// 场景热重载后重新计算包围球
+fn invalidate_scene_bounds(
+    mut scene_events: EventReader<AssetEvent<Scene>>,
+    mut scene_bounds: ResMut<SceneBounds>,
+) {
+    for event in scene_events.read() {
+        if let AssetEvent::Modified { id } | AssetEvent::Removed { id } = event {
+            scene_bounds.0.remove(id);
+        }
+    }
+}
+
+fn hide_unfitted_models(mut query: Query<&mut Visibility, Added<FitToRadius>>) {
+    for mut visibility in query.iter_mut() {
+        *visibility = Visibility::Hidden;
+    }
+}
+
+// 在场景实例生成前把场景句柄移到子实体上，缩放与平移都作用在子实体上
+fn detach_fitted_scene(trigger: Trigger<OnAdd, FitToRadius>, mut commands: Commands, scenes: Query<&Handle<Scene>>) {
+    let entity = trigger.entity();
+    let Ok(scene) = scenes.get(entity) else { return; };
+    let model = commands.spawn((
+        SceneBundle {
+            scene: scene.clone(),
+            ..default()
+        },
+        FittedModel,
+    )).id();
+    commands.entity(entity).remove::<Handle<Scene>>().add_child(model);
+}
+
+// 场景或其网格尚未加载完时保留 FitToRadius，下一帧重试
+fn fit_models_to_radius(
+    mut commands: Commands,
+    mut query: Query<(Entity, &FitToRadius, &Children, &mut Visibility)>,
+    mut models: Query<(&Handle<Scene>, &mut Transform), With<FittedModel>>,
+    scenes: Res<Assets<Scene>>,
+    meshes: Res<Assets<Mesh>>,
+    mut scene_bounds: ResMut<SceneBounds>,
+) {
+    for (entity, fit, children, mut visibility) in query.iter_mut() {
+        let Some(&model) = children.iter().find(|child| models.contains(**child)) else { continue; };
+        let Ok((scene, mut transform)) = models.get_mut(model) else { continue; };
+        let bounds = match scene_bounds.0.get(&scene.id()) {
+            Some(bounds) => *bounds,
+            None => {
+                let Some(bounds) = scenes.get(scene).and_then(|scene| compute_scene_bounds(scene, &meshes)) else { continue; };
+                scene_bounds.0.insert(scene.id(), bounds);
+                bounds
+            }
+        };
+        *transform = fitted_transform(bounds, fit.0);
+        *visibility = Visibility::Inherited;
+        commands.entity(entity).remove::<FitToRadius>();
+    }
+}
+
+/// 缩放到给定半径并把包围球球心移到原点
+fn fitted_transform(bounds: ModelBounds, radius: f32) -> Transform {
+    let scale = radius / bounds.radius;
+    Transform::from_translation(-bounds.center * scale).with_scale(Vec3::splat(scale))
+}
+
+/// 任一网格尚未加载时返回 None
+pub fn compute_scene_bounds(scene: &Scene, meshes: &Assets<Mesh>) -> Option<ModelBounds> {
+    let world = &scene.world;
+    let mut points = Vec::new();
+    for entity in world.iter_entities() {
+        let Some(mesh) = entity.get::<Handle<Mesh>>() else { continue; };
+        let mesh = meshes.get(mesh)?;
+        let Some(positions) = mesh.attribute(Mesh::ATTRIBUTE_POSITION).and_then(|positions| positions.as_float3()) else { continue; };
+        // 沿父节点链累积变换，得到相对场景根节点的变换
+        let mut affine = entity.get::<Transform>().map_or(Affine3A::IDENTITY, Transform::compute_affine);
+        let mut parent = entity.get::<Parent>();
+        while let Some(parent_entity) = parent.and_then(|parent| world.get_entity(parent.get())) {
+            affine = parent_entity.get::<Transform>().map_or(Affine3A::IDENTITY, Transform::compute_affine) * affine;
+            parent = parent_entity.get::<Parent>();
+        }
+        points.extend(positions.iter().map(|position| affine.transform_point3(Vec3::from(*position))));
+    }
+    bounding_sphere(&points)
+}
+
+/// 以包围盒中心为球心的包围球
+pub fn bounding_sphere(points: &[Vec3]) -> Option<ModelBounds> {
+    let (min, max) = points.iter().fold((Vec3::INFINITY, Vec3::NEG_INFINITY), |(min, max), point| {
+        (min.min(*point), max.max(*point))
+    });
+    let center = (min + max) / 2.0;
+    let radius = points.iter().map(|point| point.distance(center)).fold(0.0, f32::max);
+    (radius > 0.0).then_some(ModelBounds { center, radius })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn bounding_sphere_of_box_corners() {
+        let points: Vec<Vec3> = (0..8).map(|index| Vec3::new(
+            if index & 1 == 0 { 1.0 } else { 3.0 },
+            if index & 2 == 0 { -1.0 } else { 1.0 },
+            if index & 4 == 0 { -1.0 } else { 1.0 },
+        )).collect();
+        let bounds = bounding_sphere(&points).unwrap();
+        assert_eq!(bounds.center, Vec3::new(2.0, 0.0, 0.0));
+        assert!((bounds.radius - 3.0_f32.sqrt()).abs() < 1e-6);
+        assert!(bounding_sphere(&[]).is_none());
+        // 缩放后球心落在原点，最远的角点距原点为给定半径
+        let transform = fitted_transform(bounds, 6.0);
+        assert!(transform.transform_point(bounds.center).length() < 1e-5);
+        assert!((transform.transform_point(Vec3::new(3.0, 1.0, 1.0)).length() - 6.0).abs() < 1e-5);
+    }
+    #[test]
+    fn catalog_pick_is_deterministic_and_respects_role() {
+        let model = |name: &str, star| CatalogModel { name: name.to_string(), scene: Handle::default(), star, emissive: None };
+        let catalog = ModelCatalog { models: vec![model("sun", true), model("a", false), model("b", false), model("c", false)] };
+        assert_eq!(catalog.pick(Some("b"), "Earth", false).unwrap().name, "b");
+        assert_eq!(catalog.pick(None, "Sirius", true).unwrap().name, "sun");
+        let picked = catalog.pick(None, "Earth", false).unwrap();
+        assert!(!picked.star);
+        assert_eq!(catalog.pick(Some("missing"), "Earth", false).unwrap().name, picked.name);
+    }
+}
@@ -1,14 +1,22 @@
 use bevy::{
+    core_pipeline::bloom::BloomSettings,
     // input::mouse::MouseWheel,
     prelude::*
 };
 
 const CAMERA_DISTANCE: f32 = 520.0;
+
+/// 相机跟随的天体，天体被移除后停在原处
+#[derive(Resource, Debug, Default)]
+pub struct CameraFocus(pub Option<Entity>);
+
 pub struct CameraPlugin;
 
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
+        app.init_resource::<CameraFocus>();
         app.add_systems(Startup, spawn_camera);
+        app.add_systems(Update, follow_focus);
         app.add_plugins(bevy_blendy_cameras::BlendyCamerasPlugin);
         // app.add_systems(Update, handle_camera_move_operation);
         // app.add_systems(PostUpdate, handle_camera_zoom_operation);
@@ -21,14 +29,38 @@ struct GravitySystemCamera;
 fn spawn_camera(mut commands: Commands) {
     commands.spawn((
         Camera3dBundle {
+            // 恒星的自发光材质需要 HDR 才能产生泛光
+            camera: Camera {
+                hdr: true,
+                ..default()
+            },
             transform: Transform::from_xyz(0.0, CAMERA_DISTANCE, 0.0).looking_at(Vec3::ZERO, Vec3::Z),
             ..default()
         },
+        BloomSettings::NATURAL,
         bevy_blendy_cameras::OrbitCameraController::default(),
         GravitySystemCamera,
     ));
 }
 
+// 平移焦点的同时平移相机，保持视角与距离不变
+fn follow_focus(
+    mut focus: ResMut<CameraFocus>,
+    bodies: Query<&Transform, Without<GravitySystemCamera>>,
+    mut cameras: Query<(&mut Transform, &mut bevy_blendy_cameras::OrbitCameraController), With<GravitySystemCamera>>,
+) {
+    let Some(entity) = focus.0 else { return; };
+    let Ok(target) = bodies.get(entity) else {
+        focus.0 = None;
+        return;
+    };
+    for (mut transform, mut controller) in cameras.iter_mut() {
+        let offset = target.translation - controller.focus;
+        controller.focus += offset;
+        transform.translation += offset;
+    }
+}
+
 // fn handle_camera_move_operation(
 //     mut query: Query<&mut Transform, With<GravitySystemCamera>>,
 //     keyboard_input: Res<ButtonInput<KeyCode>>,
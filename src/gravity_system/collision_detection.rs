@@ -1,16 +1,19 @@
 use bevy::prelude::*;
 
-use super::GravityStatusUpdateSet;
+use super::{motion::MotionComp, GravityStatusUpdateSet};
 
 #[derive(Component)]
 pub struct CollisionDetection {
     pub radius: f32
 }
 
+/// 每对天体只发送一次，两者的先后顺序不定，处理时需同时检查 `entity` 与 `other_entity`
 #[derive(Event, Debug)]
 pub struct CollisionDetectionEvent {
     pub entity: Entity,
     pub other_entity: Entity,
+    /// 碰撞发生在本次固定步长内的时刻（秒），已重叠时为 0
+    pub time_of_impact: f32,
 }
 
 pub struct CollisionDetectionPlugin;
@@ -21,19 +24,63 @@ impl Plugin for CollisionDetectionPlugin {
     }
 }
 
+/// 连续碰撞检测：检测运行在速度更新之后、位置更新之前，
+/// 因此本步内两球的运动为 p + v * t (0 <= t <= step)
 fn collision_detection_system(
     mut events_writer: EventWriter<CollisionDetectionEvent>,
-    query: Query<(Entity, &GlobalTransform, &CollisionDetection), With<CollisionDetection>>,
+    query: Query<(Entity, &Transform, &CollisionDetection, Option<&MotionComp>)>,
+    time: Res<Time>,
 ) {
-    for (entity, transform, collection) in query.iter() {
-        for (other_entity, other_transform, other_collection) in query.iter() {
-            if entity == other_entity { continue; }
-            if transform.translation().distance(other_transform.translation()) <= collection.radius + other_collection.radius {
-                events_writer.send(CollisionDetectionEvent {
-                    entity,
-                    other_entity,
-                });
-            }
+    let step = time.delta_seconds();
+    for [
+        (entity, transform, collision, motion),
+        (other_entity, other_transform, other_collision, other_motion),
+    ] in query.iter_combinations() {
+        let velocity = motion.map_or(Vec3::ZERO, |motion| motion.velocity);
+        let other_velocity = other_motion.map_or(Vec3::ZERO, |motion| motion.velocity);
+        let time_of_impact = swept_sphere_time_of_impact(
+            other_transform.translation - transform.translation,
+            other_velocity - velocity,
+            collision.radius + other_collision.radius,
+            step,
+        );
+        if let Some(time_of_impact) = time_of_impact {
+            events_writer.send(CollisionDetectionEvent {
+                entity,
+                other_entity,
+                time_of_impact,
+            });
         }
     }
-}
\ No newline at end of file
+}
+
+/// 求相对位移为 `offset + relative_velocity * t` 的两球在 [0, step] 内首次接触的时刻
+pub fn swept_sphere_time_of_impact(offset: Vec3, relative_velocity: Vec3, radius_sum: f32, step: f32) -> Option<f32> {
+    let c = offset.length_squared() - radius_sum * radius_sum;
+    if c <= 0.0 { return Some(0.0); }
+    let a = relative_velocity.length_squared();
+    let b = offset.dot(relative_velocity);
+    // 相对静止或正在远离
+    if a <= f32::EPSILON || b >= 0.0 { return None; }
+    let discriminant = b * b - a * c;
+    if discriminant < 0.0 { return None; }
+    let time_of_impact = (-b - discriminant.sqrt()) / a;
+    (time_of_impact <= step).then_some(time_of_impact)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn fast_body_does_not_tunnel() {
+        // 每步移动 200，远大于两球半径之和 10
+        let time_of_impact = swept_sphere_time_of_impact(Vec3::new(100.0, 0.0, 0.0), Vec3::new(-200.0, 0.0, 0.0), 10.0, 1.0);
+        assert!((time_of_impact.unwrap() - 0.45).abs() < 1e-6);
+    }
+    #[test]
+    fn separating_or_distant_bodies_do_not_collide() {
+        assert_eq!(swept_sphere_time_of_impact(Vec3::new(100.0, 0.0, 0.0), Vec3::new(200.0, 0.0, 0.0), 10.0, 1.0), None);
+        assert_eq!(swept_sphere_time_of_impact(Vec3::new(100.0, 0.0, 0.0), Vec3::new(-50.0, 0.0, 0.0), 10.0, 1.0), None);
+        assert_eq!(swept_sphere_time_of_impact(Vec3::new(100.0, 20.0, 0.0), Vec3::new(-200.0, 0.0, 0.0), 10.0, 1.0), None);
+    }
+}
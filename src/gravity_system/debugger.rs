@@ -3,6 +3,7 @@ use bevy::prelude::*;
 use super::{
     motion::MotionComp,
     planet::SmallPlanet,
+    units::UnitSystem,
 };
 #[derive(Resource, Debug)]
 struct DebugTimer(Timer);
@@ -21,15 +22,20 @@ impl Plugin for DebuggerPlugin {
 fn watch_small_planet(
     time: Res<Time>,
     mut timer: ResMut<DebugTimer>,
-    query: Query<(&Transform, &MotionComp), With<SmallPlanet>>
+    query: Query<(&Transform, &MotionComp), With<SmallPlanet>>,
+    units: Res<UnitSystem>,
 ) {
     if timer.0.tick(time.delta()).just_finished() {
-        let (transform, motion) = query.single();
-        println!(
-            "******************\nsmall planet Position: {:?}\nVelocity: {:?}\nAcceleration: {:?}\n******************",
-            transform.translation,
-            motion.velocity,
-            motion.acceleration
-        );
+        for (transform, motion) in query.iter() {
+            debug!(
+                "small planet position: {:?} {}, velocity: {:?} {}, acceleration: {:?} {}",
+                transform.translation,
+                units.length_unit(),
+                motion.velocity,
+                units.velocity_unit(),
+                motion.acceleration,
+                units.acceleration_unit()
+            );
+        }
     }
 }
@@ -0,0 +1,235 @@
+use std::f32::consts::PI;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use super::{
+    collision_detection::CollisionDetection,
+    motion::SimulationTime,
+    planet::FixedStar,
+    running_state::{ResetEvent, RunningState},
+    scenario::Scenario,
+    GravityStatusUpdateSet,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EclipseKind {
+    /// 较小的天体从恒星前方经过
+    Transit,
+    /// 天体视圆面不小于恒星，可能遮住整个恒星
+    Eclipse,
+    /// 天体从恒星后方经过，被恒星遮挡
+    Occultation,
+}
+
+/// 一次凌星、食或掩星结束时发送
+#[derive(Event, Debug, Clone)]
+pub struct EclipseEvent {
+    pub kind: EclipseKind,
+    pub observer: Entity,
+    pub star: Entity,
+    pub body: Entity,
+    pub start: f64,
+    pub end: f64,
+    /// 被遮挡圆面（凌星与食为恒星，掩星为天体）面积的最大比例
+    pub max_covered_fraction: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ActiveEclipse {
+    kind: EclipseKind,
+    start: f64,
+    max_covered_fraction: f32,
+}
+
+/// 正在进行的遮挡，按 (恒星, 天体) 索引
+#[derive(Resource, Debug, Default)]
+struct ActiveEclipses(HashMap<(Entity, Entity), ActiveEclipse>);
+
+/// 光变曲线，每个固定步为每颗恒星写一行 time,star,flux；
+/// flux 为未被遮挡部分的比例乘以与初始距离相比的平方反比衰减
+#[derive(Resource, Default)]
+struct LightCurveRecorder {
+    writer: Option<BufWriter<File>>,
+    reference_distances: HashMap<Entity, f32>,
+    /// 创建或写入失败后不再重试，直到重置
+    failed: bool,
+}
+impl LightCurveRecorder {
+    fn record(&mut self, time: f64, star_name: &str, flux: f32) {
+        let Some(writer) = self.writer.as_mut() else { return; };
+        if let Err(err) = writeln!(writer, "{},{},{}", time, star_name, flux) {
+            self.fail(err);
+        }
+    }
+
+    fn flush(&mut self) {
+        let Some(writer) = self.writer.as_mut() else { return; };
+        if let Err(err) = writer.flush() {
+            self.fail(err);
+        }
+    }
+
+    fn fail(&mut self, err: std::io::Error) {
+        error!("failed to write light curve: {}", err);
+        self.writer = None;
+        self.failed = true;
+    }
+}
+
+pub struct EclipsePlugin;
+impl Plugin for EclipsePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActiveEclipses>();
+        app.init_resource::<LightCurveRecorder>();
+        app.add_event::<EclipseEvent>();
+        app.add_systems(FixedUpdate, detect_eclipses
+            .in_set(GravityStatusUpdateSet::Analysis)
+            .run_if(|scenario: Res<Scenario>| scenario.eclipses.is_some()));
+        app.add_systems(Update, log_eclipses.run_if(on_event::<EclipseEvent>()));
+        app.add_systems(PostUpdate, reset_eclipses.run_if(on_event::<ResetEvent>()));
+        app.add_systems(OnEnter(RunningState::End), flush_light_curve);
+        app.add_systems(Last, flush_light_curve.run_if(on_event::<AppExit>()));
+    }
+}
+
+fn detect_eclipses(
+    scenario: Res<Scenario>,
+    names: Query<(Entity, &Name)>,
+    bodies: Query<(Entity, &Transform, &CollisionDetection, Has<FixedStar>)>,
+    simulation_time: Res<SimulationTime>,
+    mut active: ResMut<ActiveEclipses>,
+    mut recorder: ResMut<LightCurveRecorder>,
+    mut eclipse_events: EventWriter<EclipseEvent>,
+) {
+    let Some(settings) = &scenario.eclipses else { return; };
+    let Some(observer) = names.iter().find(|(_, name)| name.as_str() == settings.observer).map(|(entity, _)| entity) else { return; };
+    let Ok((_, observer_transform, _, _)) = bodies.get(observer) else { return; };
+    let observer_position = observer_transform.translation;
+    let time = simulation_time.elapsed;
+    if recorder.writer.is_none() && !recorder.failed {
+        recorder.writer = create_light_curve(&settings.light_curve);
+        recorder.failed = recorder.writer.is_none();
+    }
+
+    for (star, star_transform, star_collision, is_star) in bodies.iter() {
+        if !is_star || star == observer { continue; }
+        let star_offset = star_transform.translation - observer_position;
+        let star_distance = star_offset.length();
+        let star_angular_radius = angular_radius(star_collision.radius, star_distance);
+        let mut covered_fraction = 0.0;
+        for (body, body_transform, body_collision, _) in bodies.iter() {
+            if body == star || body == observer { continue; }
+            let body_offset = body_transform.translation - observer_position;
+            let body_angular_radius = angular_radius(body_collision.radius, body_offset.length());
+            let separation = star_offset.angle_between(body_offset);
+            let in_front = body_offset.length() < star_distance;
+            let (kind, fraction) = if in_front {
+                let kind = if body_angular_radius >= star_angular_radius { EclipseKind::Eclipse } else { EclipseKind::Transit };
+                (kind, disk_overlap_fraction(star_angular_radius, body_angular_radius, separation))
+            } else {
+                (EclipseKind::Occultation, disk_overlap_fraction(body_angular_radius, star_angular_radius, separation))
+            };
+            if in_front {
+                covered_fraction += fraction;
+            }
+            if fraction > 0.0 {
+                let eclipse = active.0.entry((star, body)).or_insert(ActiveEclipse { kind, start: time, max_covered_fraction: 0.0 });
+                eclipse.max_covered_fraction = eclipse.max_covered_fraction.max(fraction);
+            } else if let Some(eclipse) = active.0.remove(&(star, body)) {
+                eclipse_events.send(EclipseEvent {
+                    kind: eclipse.kind,
+                    observer,
+                    star,
+                    body,
+                    start: eclipse.start,
+                    end: time,
+                    max_covered_fraction: eclipse.max_covered_fraction,
+                });
+            }
+        }
+
+        let reference_distance = *recorder.reference_distances.entry(star).or_insert(star_distance);
+        let flux = (1.0 - covered_fraction).max(0.0) * (reference_distance / star_distance).powi(2);
+        if recorder.writer.is_some() {
+            let star_name = names.get(star).map_or_else(|_| format!("{:?}", star), |(_, name)| name.to_string());
+            recorder.record(time, &star_name, flux);
+        }
+    }
+}
+
+fn create_light_curve(path: &str) -> Option<BufWriter<File>> {
+    if let Some(parent) = Path::new(path).parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    match File::create(path) {
+        Ok(file) => {
+            let mut writer = BufWriter::new(file);
+            writeln!(writer, "time,star,flux").ok()?;
+            Some(writer)
+        }
+        Err(err) => {
+            error!("failed to create light curve {}: {}", path, err);
+            None
+        }
+    }
+}
+
+fn log_eclipses(mut eclipse_events: EventReader<EclipseEvent>, names: Query<&Name>) {
+    let name = |entity: Entity| names.get(entity).map_or_else(|_| format!("{:?}", entity), |name| name.to_string());
+    for event in eclipse_events.read() {
+        info!(
+            "{:?} of {} by {} seen from {}: t = {} .. {}, max covered {:.1}%",
+            event.kind, name(event.star), name(event.body), name(event.observer),
+            event.start, event.end, event.max_covered_fraction * 100.0,
+        );
+    }
+}
+
+fn flush_light_curve(mut recorder: ResMut<LightCurveRecorder>) {
+    recorder.flush();
+}
+
+// 写出本次的光变曲线，重置后重新开始记录
+fn reset_eclipses(mut active: ResMut<ActiveEclipses>, mut recorder: ResMut<LightCurveRecorder>) {
+    active.0.clear();
+    recorder.flush();
+    *recorder = LightCurveRecorder::default();
+}
+
+fn angular_radius(radius: f32, distance: f32) -> f32 {
+    (radius / distance).clamp(-1.0, 1.0).asin()
+}
+
+/// 圆面 a 被圆面 b 遮住的面积比例，`separation` 为两圆心距离，三者同一单位
+pub fn disk_overlap_fraction(radius_a: f32, radius_b: f32, separation: f32) -> f32 {
+    if radius_a <= 0.0 || separation >= radius_a + radius_b { return 0.0; }
+    if separation <= (radius_b - radius_a).max(0.0) { return 1.0; }
+    if separation <= radius_a - radius_b { return (radius_b / radius_a).powi(2); }
+    // 两圆部分相交时的透镜面积
+    let (a2, b2, d2) = (radius_a.powi(2), radius_b.powi(2), separation.powi(2));
+    let angle_a = ((d2 + a2 - b2) / (2.0 * separation * radius_a)).clamp(-1.0, 1.0).acos();
+    let angle_b = ((d2 + b2 - a2) / (2.0 * separation * radius_b)).clamp(-1.0, 1.0).acos();
+    let kite = 0.5 * ((-separation + radius_a + radius_b)
+        * (separation + radius_a - radius_b)
+        * (separation - radius_a + radius_b)
+        * (separation + radius_a + radius_b)).max(0.0).sqrt();
+    (a2 * angle_a + b2 * angle_b - kite) / (PI * a2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn disk_overlap_limits() {
+        assert_eq!(disk_overlap_fraction(1.0, 0.1, 2.0), 0.0);
+        assert!((disk_overlap_fraction(1.0, 0.1, 0.5) - 0.01).abs() < 1e-6);
+        assert_eq!(disk_overlap_fraction(1.0, 2.0, 0.5), 1.0);
+        // 等大圆心距为半径时重叠比例为 (2π/3 - √3/2) / π
+        let expected = (2.0 * PI / 3.0 - 3.0_f32.sqrt() / 2.0) / PI;
+        assert!((disk_overlap_fraction(1.0, 1.0, 1.0) - expected).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,156 @@
+use bevy::prelude::*;
+
+use super::{
+    collision_detection::CollisionDetection,
+    gravitation::{barycenter, GravitationComp},
+    motion::{MotionComp, SimulationTime},
+    particle::TestParticle,
+    running_state::ResetEvent,
+    scenario::{EscapePolicy, Scenario},
+    units::UnitSystem,
+    GravityStatusUpdateSet,
+};
+
+/// 相对系统质心的轨道是否束缚，每个固定步更新
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrbitBinding {
+    Bound,
+    Unbound,
+}
+
+/// 已触发过逃逸事件
+#[derive(Component)]
+pub struct Escaped;
+
+#[derive(Event, Debug)]
+pub struct EscapeEvent {
+    pub entity: Entity,
+    pub time: f64,
+    pub distance: f32,
+    /// 相对系统质心的速度
+    pub velocity: Vec3,
+}
+
+#[derive(Debug, Clone)]
+pub struct EscapeRecord {
+    pub name: String,
+    pub time: f64,
+    pub velocity: Vec3,
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct EscapeStats {
+    pub escapes: Vec<EscapeRecord>,
+}
+
+type EscapeBodyQuery<'a> = (Entity, &'a Transform, &'a MotionComp, Option<&'a GravitationComp>, Option<&'a OrbitBinding>, Has<Escaped>);
+
+pub struct EscapePlugin;
+impl Plugin for EscapePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EscapeStats>();
+        app.add_event::<EscapeEvent>();
+        app.add_systems(FixedUpdate,
+            (classify_bodies, handle_escape).chain().in_set(GravityStatusUpdateSet::Analysis));
+        app.add_systems(PostUpdate, reset_escape_stats.run_if(on_event::<ResetEvent>()));
+    }
+}
+
+// 比轨道能量 v²/2 - G(M - m)/r 大于 0 即为双曲轨道
+fn classify_bodies(
+    mut commands: Commands,
+    bodies: Query<EscapeBodyQuery, Without<TestParticle>>,
+    scenario: Res<Scenario>,
+    units: Res<UnitSystem>,
+    simulation_time: Res<SimulationTime>,
+    mut escape_events: EventWriter<EscapeEvent>,
+) {
+    let Some((center, center_velocity, total_mass)) = barycenter(bodies.iter().filter_map(|(_, transform, motion, gravitation, ..)| {
+        gravitation.map(|gravitation| (transform.translation, motion.velocity, gravitation.mass))
+    })) else { return; };
+    let gravitational_constant = units.gravitational_constant();
+    for (entity, transform, motion, gravitation, binding, escaped) in bodies.iter() {
+        let offset = transform.translation - center;
+        let velocity = motion.velocity - center_velocity;
+        let other_mass = total_mass - gravitation.map_or(0.0, |gravitation| gravitation.mass);
+        let new_binding = orbit_binding(offset, velocity, gravitational_constant * other_mass);
+        if binding != Some(&new_binding) {
+            commands.entity(entity).insert(new_binding);
+        }
+        if new_binding == OrbitBinding::Unbound && !escaped && offset.length() > scenario.escape.radius {
+            commands.entity(entity).insert(Escaped);
+            escape_events.send(EscapeEvent {
+                entity,
+                time: simulation_time.elapsed,
+                distance: offset.length(),
+                velocity,
+            });
+        }
+    }
+}
+
+/// `offset` 与 `velocity` 相对系统质心，`mu` 为引力常数与其余天体总质量之积
+fn orbit_binding(offset: Vec3, velocity: Vec3, mu: f32) -> OrbitBinding {
+    let energy = velocity.length_squared() / 2.0 - mu / offset.length();
+    if energy > 0.0 { OrbitBinding::Unbound } else { OrbitBinding::Bound }
+}
+
+fn handle_escape(
+    mut commands: Commands,
+    mut escape_events: EventReader<EscapeEvent>,
+    names: Query<&Name>,
+    scenario: Res<Scenario>,
+    mut stats: ResMut<EscapeStats>,
+) {
+    for event in escape_events.read() {
+        let name = names.get(event.entity).map_or_else(|_| format!("{:?}", event.entity), |name| name.to_string());
+        info!("{} escaped at t = {}, distance {}, velocity {:?}", name, event.time, event.distance, event.velocity);
+        stats.escapes.push(EscapeRecord {
+            name,
+            time: event.time,
+            velocity: event.velocity,
+        });
+        match scenario.escape.policy {
+            EscapePolicy::Despawn => commands.entity(event.entity).despawn_recursive(),
+            EscapePolicy::Freeze => {
+                commands.entity(event.entity).remove::<(MotionComp, GravitationComp, CollisionDetection)>();
+            }
+            EscapePolicy::Keep => (),
+        }
+    }
+}
+
+fn reset_escape_stats(mut stats: ResMut<EscapeStats>) {
+    stats.escapes.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+    use crate::gravity_system::scenario::EscapeJson;
+    #[test]
+    fn escape_speed_separates_bound_and_unbound() {
+        // 逃逸速度 sqrt(2μ/r) = 10
+        let (offset, mu) = (Vec3::new(100.0, 0.0, 0.0), 5000.0);
+        assert_eq!(orbit_binding(offset, Vec3::new(0.0, 9.9, 0.0), mu), OrbitBinding::Bound);
+        assert_eq!(orbit_binding(offset, Vec3::new(0.0, 0.0, 10.1), mu), OrbitBinding::Unbound);
+    }
+    #[test]
+    fn freeze_policy_removes_motion() {
+        let mut world = World::new();
+        world.insert_resource(Scenario {
+            escape: EscapeJson { policy: EscapePolicy::Freeze, ..default() },
+            ..default()
+        });
+        world.init_resource::<EscapeStats>();
+        world.init_resource::<Events<EscapeEvent>>();
+        let entity = world.spawn((MotionComp::default(), GravitationComp::new(1.0), CollisionDetection { radius: 1.0 }, Escaped)).id();
+        world.send_event(EscapeEvent { entity, time: 1.0, distance: 100.0, velocity: Vec3::X });
+        world.run_system_once(handle_escape);
+        let frozen = world.entity(entity);
+        assert!(!frozen.contains::<MotionComp>() && !frozen.contains::<GravitationComp>() && !frozen.contains::<CollisionDetection>());
+        assert_eq!(world.resource::<EscapeStats>().escapes.len(), 1);
+    }
+}
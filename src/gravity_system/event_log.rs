@@ -0,0 +1,221 @@
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+
+use bevy::input::common_conditions::input_just_pressed;
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use bevy::prelude::*;
+
+use super::{
+    orbit_events::{ApsisEvent, ApsisKind, CloseApproachEvent},
+    running_state::ResetEvent,
+    units::UnitSystem,
+};
+
+const EXPORT_PATH: &str = "output/events.csv";
+const LINE_HEIGHT: f32 = 20.0;
+// 面板只保留最近的若干行，更早的事件仍可导出
+const MAX_ROWS: usize = 200;
+
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    pub time: f64,
+    pub kind: &'static str,
+    pub bodies: String,
+    pub distance: f32,
+    pub relative_speed: f32,
+}
+
+/// 轨道事件记录，按 L 键导出为 CSV
+#[derive(Resource, Debug, Default)]
+pub struct EventLog {
+    pub entries: Vec<LogEntry>,
+}
+
+#[derive(Component)]
+struct EventLogPanel;
+
+#[derive(Component, Default)]
+struct EventLogList {
+    scroll: f32,
+    shown: usize,
+    /// 已显示的行，最早的在前
+    rows: VecDeque<Entity>,
+}
+
+pub struct EventLogPlugin;
+impl Plugin for EventLogPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EventLog>();
+        app.add_systems(Startup, spawn_event_log_panel);
+        app.add_systems(Update, (collect_events, update_event_log_panel, scroll_event_log_panel).chain());
+        app.add_systems(Update, export_event_log.run_if(input_just_pressed(KeyCode::KeyL)));
+        app.add_systems(PostUpdate, clear_event_log.run_if(on_event::<ResetEvent>()));
+    }
+}
+
+fn collect_events(
+    mut apsis_events: EventReader<ApsisEvent>,
+    mut approach_events: EventReader<CloseApproachEvent>,
+    names: Query<&Name>,
+    mut log: ResMut<EventLog>,
+) {
+    let name = |entity: Entity| names.get(entity).map_or_else(|_| format!("{:?}", entity), |name| name.to_string());
+    for event in apsis_events.read() {
+        log.entries.push(LogEntry {
+            time: event.time,
+            kind: match event.kind {
+                ApsisKind::Periapsis => "periapsis",
+                ApsisKind::Apoapsis => "apoapsis",
+            },
+            bodies: format!("{} around {}", name(event.body), name(event.attractor)),
+            distance: event.distance,
+            relative_speed: event.relative_speed,
+        });
+    }
+    for event in approach_events.read() {
+        log.entries.push(LogEntry {
+            time: event.time,
+            kind: "close approach",
+            bodies: format!("{} and {}", name(event.body), name(event.other)),
+            distance: event.distance,
+            relative_speed: event.relative_speed,
+        });
+    }
+}
+
+fn spawn_event_log_panel(mut commands: Commands) {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(10.),
+                right: Val::Px(10.),
+                width: Val::Px(520.),
+                height: Val::Px(200.),
+                overflow: Overflow::clip_y(),
+                ..default()
+            },
+            background_color: Color::srgba(0.0, 0.0, 0.0, 0.4).into(),
+            ..default()
+        },
+        Interaction::default(),
+        EventLogPanel,
+    )).with_children(|parent| {
+        parent.spawn((
+            NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Column,
+                    align_self: AlignSelf::Stretch,
+                    padding: UiRect::all(Val::Px(4.)),
+                    ..default()
+                },
+                ..default()
+            },
+            EventLogList::default(),
+        ));
+    });
+}
+
+// 最新的事件显示在最上方
+fn update_event_log_panel(
+    mut commands: Commands,
+    mut lists: Query<(Entity, &mut EventLogList)>,
+    log: Res<EventLog>,
+    units: Res<UnitSystem>,
+) {
+    let Ok((entity, mut list)) = lists.get_single_mut() else { return; };
+    if list.shown == log.entries.len() { return; }
+    let start = list.shown.max(log.entries.len().saturating_sub(MAX_ROWS));
+    for entry in log.entries[start..].iter() {
+        let row = commands.spawn(TextBundle::from_section(
+            format!(
+                "t = {:.2} {}  {}: {}, d = {:.3} {}, v = {:.3} {}",
+                entry.time, units.time_unit(), entry.kind, entry.bodies,
+                entry.distance, units.length_unit(), entry.relative_speed, units.velocity_unit(),
+            ),
+            TextStyle {
+                font_size: 16.,
+                color: Color::WHITE,
+                ..default()
+            },
+        ).with_style(Style {
+            height: Val::Px(LINE_HEIGHT),
+            ..default()
+        })).id();
+        commands.entity(entity).insert_children(0, &[row]);
+        list.rows.push_back(row);
+    }
+    while list.rows.len() > MAX_ROWS {
+        if let Some(row) = list.rows.pop_front() {
+            commands.entity(row).despawn_recursive();
+        }
+    }
+    list.shown = log.entries.len();
+}
+
+fn scroll_event_log_panel(
+    mut wheel_events: EventReader<MouseWheel>,
+    panels: Query<(&Interaction, &Node), With<EventLogPanel>>,
+    mut lists: Query<(&mut EventLogList, &mut Style, &Node)>,
+) {
+    let Ok((interaction, panel)) = panels.get_single() else { return; };
+    let Ok((mut list, mut style, node)) = lists.get_single_mut() else { return; };
+    let hovered = *interaction != Interaction::None;
+    for event in wheel_events.read() {
+        if !hovered { continue; }
+        let lines = match event.unit {
+            MouseScrollUnit::Line => event.y * LINE_HEIGHT,
+            MouseScrollUnit::Pixel => event.y,
+        };
+        let max_scroll = (node.size().y - panel.size().y).max(0.0);
+        list.scroll = (list.scroll + lines).clamp(-max_scroll, 0.0);
+        style.top = Val::Px(list.scroll);
+    }
+}
+
+fn export_event_log(log: Res<EventLog>, units: Res<UnitSystem>) {
+    let result = fs::create_dir_all("output").and_then(|_| {
+        let mut writer = BufWriter::new(File::create(EXPORT_PATH)?);
+        writeln!(
+            writer, "time ({}),kind,bodies,distance ({}),relative speed ({})",
+            units.time_unit(), units.length_unit(), units.velocity_unit(),
+        )?;
+        for entry in log.entries.iter() {
+            writeln!(writer, "{},{},{},{},{}", entry.time, entry.kind, quote_csv(&entry.bodies), entry.distance, entry.relative_speed)?;
+        }
+        writer.flush()
+    });
+    match result {
+        Ok(()) => info!("{} events exported to {}", log.entries.len(), EXPORT_PATH),
+        Err(err) => error!("failed to export events to {}: {}", EXPORT_PATH, err),
+    }
+}
+
+// 天体名可能含逗号或引号
+fn quote_csv(field: &str) -> String {
+    format!("\"{}\"", field.replace('"', "\"\""))
+}
+
+fn clear_event_log(
+    mut commands: Commands,
+    mut log: ResMut<EventLog>,
+    mut lists: Query<(Entity, &mut EventLogList, &mut Style)>,
+) {
+    log.entries.clear();
+    for (entity, mut list, mut style) in lists.iter_mut() {
+        commands.entity(entity).despawn_descendants();
+        *list = EventLogList::default();
+        style.top = Val::Px(0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn csv_fields_are_quoted_and_escaped() {
+        assert_eq!(quote_csv("Blue I and Red"), "\"Blue I and Red\"");
+        assert_eq!(quote_csv("\"A, B\" around Sun"), "\"\"\"A, B\"\" around Sun\"");
+    }
+}
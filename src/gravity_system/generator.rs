@@ -0,0 +1,242 @@
+use std::f32::consts::TAU;
+use std::ops::Range;
+
+use bevy::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::loading_state::AppState;
+
+use super::{
+    orbit::OrbitalElements,
+    running_state::{ResetEvent, RunningState},
+    scenario::{arg_value, PlanetJson, Scenario},
+    units::UnitSystem,
+};
+
+const GENERATED_DIR: &str = "assets/json/scenarios/generated";
+const STAR_MASS: f32 = 1.0e16;
+const STAR_RADIUS: f32 = 8.0;
+const BINARY_SEPARATION: f32 = 30.0;
+const INNER_ORBIT: f32 = 60.0;
+// 环双星轨道需在双星间距数倍以外才稳定
+const CIRCUMBINARY_INNER_ORBIT_FACTOR: f32 = 4.0;
+const PLANET_MASS_EXPONENT_RANGE: Range<f32> = 11.0..13.0;
+// 相邻行星间距，以互希尔半径为单位
+const HILL_SPACING_RANGE: Range<f32> = 8.0..12.0;
+const MAX_ECCENTRICITY: f32 = 0.05;
+const MAX_INCLINATION_DEGREES: f32 = 3.0;
+const MAX_MOONS: u32 = 2;
+const MOON_MASS_RATIO: Range<f32> = 1.0e-4..1.0e-2;
+
+#[derive(Resource, Debug, Clone)]
+pub struct GeneratorSettings {
+    pub seed: u64,
+    /// 1 为单星，2 为双星
+    pub star_count: u32,
+    pub planet_count: u32,
+    pub moons: bool,
+}
+impl Default for GeneratorSettings {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            star_count: 1,
+            planet_count: 5,
+            moons: true,
+        }
+    }
+}
+impl GeneratorSettings {
+    /// `--seed <n> --stars <1|2> --planets <n> --no-moons`
+    pub fn from_args(args: &[String]) -> Self {
+        let default = Self::default();
+        Self {
+            seed: arg_value(args, "--seed").and_then(|value| value.parse().ok()).unwrap_or(default.seed),
+            star_count: arg_value(args, "--stars").and_then(|value| value.parse().ok()).unwrap_or(default.star_count).clamp(1, 2),
+            planet_count: arg_value(args, "--planets").and_then(|value| value.parse().ok()).unwrap_or(default.planet_count),
+            moons: !args.iter().any(|arg| arg == "--no-moons"),
+        }
+    }
+}
+
+#[derive(Component)]
+struct GenerateButton;
+
+pub struct GeneratorPlugin;
+impl Plugin for GeneratorPlugin {
+    fn build(&self, app: &mut App) {
+        let args: Vec<String> = std::env::args().collect();
+        app.insert_resource(GeneratorSettings::from_args(&args));
+        app.add_systems(Startup, spawn_generate_button);
+        app.add_systems(Update, handle_generate_button.run_if(in_state(AppState::Running)));
+    }
+}
+
+/// 生成星系，同时写出场景文件
+pub fn generate_and_save(settings: &GeneratorSettings) -> Scenario {
+    let scenario = generate(settings);
+    let path = format!("{}/seed-{}.json", GENERATED_DIR, settings.seed);
+    match scenario.save(&path) {
+        Ok(()) => info!("generated scenario saved to {}", path),
+        Err(err) => error!("failed to save generated scenario to {}: {}", path, err),
+    }
+    scenario
+}
+
+pub fn generate(settings: &GeneratorSettings) -> Scenario {
+    let mut rng = StdRng::seed_from_u64(settings.seed);
+    // 生成的星系沿用国际单位制，质量与距离尺度同 fixed_stars.json
+    let mut scenario = Scenario::default();
+    let gravitational_constant = UnitSystem::Si.gravitational_constant();
+    let central_mass = if settings.star_count >= 2 {
+        let masses = [STAR_MASS * rng.gen_range(0.4..0.6), STAR_MASS * rng.gen_range(0.4..0.6)];
+        let total_mass = masses[0] + masses[1];
+        // 两颗恒星绕原点处的质心做圆轨道
+        let (position, velocity) = OrbitalElements {
+            semi_major_axis: BINARY_SEPARATION,
+            eccentricity: 0.0,
+            inclination: 0.0,
+            ascending_node: 0.0,
+            argument_of_periapsis: 0.0,
+            true_anomaly: rng.gen_range(0.0..TAU),
+        }.to_state_vectors(gravitational_constant * total_mass).unwrap();
+        scenario.fixed_stars.push(body("Star A", masses[0], -position * masses[1] / total_mass, -velocity * masses[1] / total_mass, STAR_RADIUS));
+        scenario.fixed_stars.push(body("Star B", masses[1], position * masses[0] / total_mass, velocity * masses[0] / total_mass, STAR_RADIUS));
+        total_mass
+    } else {
+        let mass = STAR_MASS * rng.gen_range(0.8..1.2);
+        scenario.fixed_stars.push(body("Star", mass, Vec3::ZERO, Vec3::ZERO, STAR_RADIUS));
+        mass
+    };
+
+    let mut semi_major_axis = if settings.star_count >= 2 {
+        BINARY_SEPARATION * CIRCUMBINARY_INNER_ORBIT_FACTOR
+    } else {
+        INNER_ORBIT
+    };
+    let mut previous_mass: Option<f32> = None;
+    for index in 0..settings.planet_count {
+        let mass = 10.0_f32.powf(rng.gen_range(PLANET_MASS_EXPONENT_RANGE));
+        if let Some(previous_mass) = previous_mass {
+            // a2 - a1 = K * R_H，其中互希尔半径 R_H = ((m1 + m2) / 3M)^(1/3) * (a1 + a2) / 2
+            let half_spacing = rng.gen_range(HILL_SPACING_RANGE) * ((previous_mass + mass) / (3.0 * central_mass)).cbrt() / 2.0;
+            semi_major_axis *= (1.0 + half_spacing) / (1.0 - half_spacing);
+        }
+        previous_mass = Some(mass);
+        let mu = gravitational_constant * (central_mass + mass);
+        let (position, velocity) = random_near_circular_orbit(&mut rng, semi_major_axis).to_state_vectors(mu).unwrap();
+        let name = format!("Planet {}", index + 1);
+        let radius = planet_radius(mass);
+        scenario.planets.push(body(&name, mass, position, velocity, radius));
+        if settings.moons {
+            let hill_radius = semi_major_axis * (mass / (3.0 * central_mass)).cbrt();
+            let moon_orbits = 2.5 * radius..0.4 * hill_radius;
+            let mut moon_semi_major_axis = moon_orbits.start;
+            for moon_index in 0..rng.gen_range(0..=MAX_MOONS) {
+                moon_semi_major_axis *= rng.gen_range(1.2..1.8);
+                if !moon_orbits.contains(&moon_semi_major_axis) { break; }
+                let moon_mass = mass * rng.gen_range(MOON_MASS_RATIO);
+                let (moon_position, moon_velocity) = random_near_circular_orbit(&mut rng, moon_semi_major_axis)
+                    .to_state_vectors(gravitational_constant * (mass + moon_mass))
+                    .unwrap();
+                scenario.planets.push(body(
+                    &format!("{} {}", name, ["I", "II"][moon_index as usize]),
+                    moon_mass,
+                    position + moon_position,
+                    velocity + moon_velocity,
+                    planet_radius(moon_mass),
+                ));
+            }
+        }
+    }
+    scenario
+}
+
+fn random_near_circular_orbit(rng: &mut StdRng, semi_major_axis: f32) -> OrbitalElements {
+    OrbitalElements {
+        semi_major_axis,
+        eccentricity: rng.gen_range(0.0..MAX_ECCENTRICITY),
+        inclination: rng.gen_range(0.0..MAX_INCLINATION_DEGREES.to_radians()),
+        ascending_node: rng.gen_range(0.0..TAU),
+        argument_of_periapsis: rng.gen_range(0.0..TAU),
+        true_anomaly: rng.gen_range(0.0..TAU),
+    }
+}
+
+fn planet_radius(mass: f32) -> f32 {
+    0.5 + 0.5 * (mass / 1.0e11).cbrt()
+}
+
+fn body(name: &str, mass: f32, position: Vec3, velocity: Vec3, radius: f32) -> PlanetJson {
+    PlanetJson {
+        name: Some(name.to_string()),
+        mass,
+        position: position.into(),
+        velocity: velocity.into(),
+        radius,
+        parent: None,
+        orbit: None,
+        spin: None,
+        model: None,
+        emissive: None,
+        light: None,
+    }
+}
+
+fn spawn_generate_button(mut commands: Commands) {
+    commands.spawn((
+        ButtonBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(10.),
+                right: Val::Px(10.),
+                padding: UiRect::all(Val::Px(8.)),
+                ..default()
+            },
+            background_color: Color::srgba(1.0, 1.0, 1.0, 0.15).into(),
+            ..default()
+        },
+        GenerateButton,
+    )).with_children(|parent| {
+        parent.spawn(TextBundle::from_section(
+            "Generate system",
+            TextStyle {
+                font_size: 20.,
+                color: Color::WHITE,
+                ..default()
+            }
+        ));
+    });
+}
+
+// 每次点击使用下一个种子
+fn handle_generate_button(
+    interactions: Query<&Interaction, (Changed<Interaction>, With<GenerateButton>)>,
+    mut settings: ResMut<GeneratorSettings>,
+    mut scenario: ResMut<Scenario>,
+    mut reset_event_writer: EventWriter<ResetEvent>,
+    mut next_state: ResMut<NextState<RunningState>>,
+) {
+    for interaction in interactions.iter() {
+        if *interaction != Interaction::Pressed { continue; }
+        settings.seed += 1;
+        *scenario = generate_and_save(&settings);
+        reset_event_writer.send(ResetEvent);
+        next_state.set(RunningState::Running);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn generated_planets_are_spaced_and_deterministic() {
+        let settings = GeneratorSettings { seed: 7, star_count: 2, planet_count: 6, moons: false };
+        let scenario = generate(&settings);
+        assert_eq!(scenario.fixed_stars.len(), 2);
+        assert_eq!(scenario.planets.len(), 6);
+        let distances: Vec<f32> = scenario.planets.iter().map(|planet| Vec3::from(planet.position).length()).collect();
+        assert!(distances.windows(2).all(|pair| pair[1] > pair[0]));
+        assert_eq!(format!("{:?}", generate(&settings)), format!("{:?}", scenario));
+    }
+}
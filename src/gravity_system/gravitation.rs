@@ -1,5 +1,8 @@
-use bevy::{prelude::*, utils::HashMap};
-use super::{motion::MotionComp, GravityStatusUpdateSet};
+use bevy::input::common_conditions::input_just_pressed;
+use bevy::prelude::*;
+use super::{motion::{BodyId, MotionComp}, particle::TestParticle, replay::replaying, units::UnitSystem, GravityStatusUpdateSet};
+
+const BARYCENTER_MARKER_SCALE: f32 = 0.02;
 
 #[derive(Component)]
 pub struct GravitationComp {
@@ -11,41 +14,197 @@ impl GravitationComp {
     }
 }
 
+/// 一阶后牛顿修正的开关与光速（场景单位制），按 G 键切换
+#[derive(Resource, Debug)]
+pub struct PostNewtonian {
+    pub enabled: bool,
+    pub speed_of_light: f32,
+}
+impl Default for PostNewtonian {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            speed_of_light: UnitSystem::default().speed_of_light(),
+        }
+    }
+}
+
+/// EIH 修正中引力源所需的状态，`acceleration` 与 `potential` 为其他引力源在该处产生的牛顿加速度与势
+#[derive(Debug, Clone, Copy)]
+pub struct PostNewtonianSource {
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub mu: f32,
+    pub acceleration: Vec3,
+    pub potential: f32,
+}
+
 pub struct GravitationPlugin;
 impl Plugin for GravitationPlugin {
     fn build(&self, app: &mut App) {
+        app.init_resource::<UnitSystem>();
+        app.init_resource::<PostNewtonian>();
         app.add_systems(FixedUpdate,
             acceleration_update.chain().in_set(GravityStatusUpdateSet::AccelerationUpdate));
+        app.add_systems(Update, draw_barycenter);
+        app.add_systems(Update, toggle_post_newtonian.run_if(input_just_pressed(KeyCode::KeyG).and_then(not(replaying))));
     }
 }
 
+// 有质量的天体作为引力源，所有带 MotionComp 的实体（包括测试粒子）作为受力者，
+// 复杂度为 O(受力者 × 引力源)。引力源按 BodyId 排序后求和，结果与实体在表中的顺序无关
 fn acceleration_update(
-    mut query: Query<(Entity, &GlobalTransform, &GravitationComp, &mut MotionComp)>
+    attractors: Query<(Entity, &BodyId, &Transform, &GravitationComp), Without<TestParticle>>,
+    mut receivers: Query<(Entity, &Transform, &mut MotionComp)>,
+    units: Res<UnitSystem>,
+    post_newtonian: Res<PostNewtonian>,
 ) {
-    let mut new_accelerations_map: HashMap<Entity, Vec3> = HashMap::new();
-    for (entity, transform, _, _) in query.iter() {
-        let mut acclerations = Vec::new();
-        for (other_entity ,other_transform, other_gravitation, _) in query.iter() {
-            if entity == other_entity { continue; }
-            let distance = transform.translation().distance(other_transform.translation());
-            let acceleration = 6.67 * 10.0_f32.powi(-11) * other_gravitation.mass / distance.powi(2);
-            let dir = (other_transform.translation() - transform.translation()).normalize();
-            acclerations.push(dir*acceleration);
+    let gravitational_constant = units.gravitational_constant();
+    let mut attractors: Vec<(Entity, &BodyId, Vec3, f32)> = attractors.iter()
+        .filter(|(_, _, _, gravitation)| gravitation.mass > 0.0)
+        .map(|(entity, id, transform, gravitation)| (entity, id, transform.translation, gravitation.mass))
+        .collect();
+    attractors.sort_by_key(|(_, id, ..)| **id);
+    let attractors: Vec<(Entity, Vec3, f32)> = attractors.into_iter()
+        .map(|(entity, _, position, mass)| (entity, position, mass))
+        .collect();
+    let sources = post_newtonian.enabled.then(|| post_newtonian_sources(&attractors.iter()
+        .map(|(entity, position, mass)| {
+            let velocity = receivers.get(*entity).map_or(Vec3::ZERO, |(_, _, motion)| motion.velocity);
+            (*position, velocity, gravitational_constant * mass)
+        })
+        .collect::<Vec<_>>()));
+    for (entity, transform, mut motion) in receivers.iter_mut() {
+        motion.acceleration = attractors.iter()
+            .filter(|(other_entity, _, _)| *other_entity != entity)
+            .map(|(_, position, mass)| gravitational_acceleration(transform.translation, *position, gravitational_constant * mass))
+            .sum();
+        if let Some(sources) = &sources {
+            let index = attractors.iter().position(|(other_entity, ..)| *other_entity == entity);
+            let correction = post_newtonian_correction(
+                transform.translation, motion.velocity, sources, index, post_newtonian.speed_of_light,
+            );
+            motion.acceleration += correction;
         }
-        new_accelerations_map.insert(entity, acclerations.iter().sum::<Vec3>());
     }
-    for (entity, _, _, mut motion) in query.iter_mut() {
-        motion.acceleration = *new_accelerations_map.get(&entity).unwrap();
+}
+
+fn toggle_post_newtonian(mut post_newtonian: ResMut<PostNewtonian>) {
+    post_newtonian.enabled = !post_newtonian.enabled;
+    info!("Post-Newtonian correction {} (c = {})",
+        if post_newtonian.enabled { "enabled" } else { "disabled" }, post_newtonian.speed_of_light);
+}
+
+// 质心标记的大小随天体分布范围缩放，不同单位制下都可见
+fn draw_barycenter(
+    mut gizmos: Gizmos,
+    bodies: Query<(&Transform, &MotionComp, &GravitationComp), Without<TestParticle>>,
+) {
+    let bodies: Vec<(Vec3, Vec3, f32)> = bodies.iter()
+        .map(|(transform, motion, gravitation)| (transform.translation, motion.velocity, gravitation.mass))
+        .collect();
+    let Some((center, _, _)) = barycenter(bodies.iter().copied()) else { return; };
+    let extent = bodies.iter().map(|(position, _, _)| position.distance(center)).fold(0.0, f32::max);
+    let size = (extent * BARYCENTER_MARKER_SCALE).max(f32::EPSILON);
+    let color = Color::srgb(1.0, 0.3, 0.3);
+    for axis in [Vec3::X, Vec3::Y, Vec3::Z] {
+        gizmos.line(center - axis * size, center + axis * size, color);
     }
+    gizmos.sphere(center, Quat::IDENTITY, size / 2.0, color);
+}
+
+/// 由 (位置, 速度, 质量) 求质心的位置、速度与总质量
+pub fn barycenter(bodies: impl IntoIterator<Item = (Vec3, Vec3, f32)>) -> Option<(Vec3, Vec3, f32)> {
+    let (moment, momentum, total_mass) = bodies.into_iter()
+        .fold((Vec3::ZERO, Vec3::ZERO, 0.0), |(moment, momentum, total_mass), (position, velocity, mass)| {
+            (moment + position * mass, momentum + velocity * mass, total_mass + mass)
+        });
+    (total_mass > 0.0).then(|| (moment / total_mass, momentum / total_mass, total_mass))
+}
+
+/// `mu` 为引力常数与引力源质量之积
+pub fn gravitational_acceleration(position: Vec3, attractor_position: Vec3, mu: f32) -> Vec3 {
+    let distance = position.distance(attractor_position);
+    let acceleration = mu / distance.powi(2);
+    (attractor_position - position).normalize() * acceleration
+}
+
+/// 由 (位置, 速度, μ) 求各引力源处的牛顿加速度与势，供 EIH 修正使用
+pub fn post_newtonian_sources(bodies: &[(Vec3, Vec3, f32)]) -> Vec<PostNewtonianSource> {
+    bodies.iter().enumerate().map(|(index, (position, velocity, mu))| {
+        let others = bodies.iter().enumerate().filter(|(other, _)| *other != index);
+        PostNewtonianSource {
+            position: *position,
+            velocity: *velocity,
+            mu: *mu,
+            acceleration: others.clone().map(|(_, (other, _, other_mu))| gravitational_acceleration(*position, *other, *other_mu)).sum(),
+            potential: others.map(|(_, (other, _, other_mu))| other_mu / position.distance(*other)).sum(),
+        }
+    }).collect()
+}
+
+/// Einstein–Infeld–Hoffmann 方程相对牛顿引力的一阶修正。
+/// `skip` 为受力者自身在 `sources` 中的序号，测试粒子为 None
+pub fn post_newtonian_correction(position: Vec3, velocity: Vec3, sources: &[PostNewtonianSource], skip: Option<usize>, speed_of_light: f32) -> Vec3 {
+    let inverse_c2 = 1.0 / speed_of_light.powi(2);
+    let others = || sources.iter().enumerate().filter(move |(index, _)| Some(*index) != skip).map(|(_, source)| source);
+    let potential: f32 = others().map(|source| source.mu / position.distance(source.position)).sum();
+    others().map(|source| {
+        let separation = position - source.position;
+        let distance = separation.length();
+        let mu_over_r3 = source.mu / distance.powi(3);
+        let radial_velocity = separation.dot(source.velocity) / distance;
+        let bracket = -4.0 * potential - source.potential
+            + velocity.length_squared() + 2.0 * source.velocity.length_squared()
+            - 4.0 * velocity.dot(source.velocity)
+            - 1.5 * radial_velocity.powi(2)
+            - 0.5 * separation.dot(source.acceleration);
+        -separation * mu_over_r3 * bracket
+            + (velocity - source.velocity) * mu_over_r3 * separation.dot(4.0 * velocity - 3.0 * source.velocity)
+            + source.acceleration * 3.5 * source.mu / distance
+    }).sum::<Vec3>() * inverse_c2
 }
 
 #[cfg(test)]
 mod tests {
     use bevy::prelude::*;
+    use super::*;
   #[test]
   fn it_works() {
     let position1 = Vec3::ZERO;
     let position2 = Vec3::new(2.0, 0.0, 0.0);
     println!("{:?}", (position2 - position1).normalize());
   }
+
+  // 绕 μ = 1 的静止恒星，a = 1、e = 0.6，返回每圈近日点转过的角度
+  fn periapsis_advance(speed_of_light: Option<f32>) -> f32 {
+    let star = post_newtonian_sources(&[(Vec3::ZERO, Vec3::ZERO, 1.0)]);
+    let (mut position, mut velocity) = (Vec3::X * 0.4, Vec3::Z * 2.0);
+    let dt = 2.0e-4;
+    let mut periapses = Vec::new();
+    for _ in 0..(4.5 * std::f32::consts::TAU / dt) as usize {
+      let mut acceleration = gravitational_acceleration(position, Vec3::ZERO, 1.0);
+      if let Some(speed_of_light) = speed_of_light {
+        acceleration += post_newtonian_correction(position, velocity, &star, None, speed_of_light);
+      }
+      let approaching = position.dot(velocity) < 0.0;
+      velocity += acceleration * dt;
+      position += velocity * dt;
+      // 径向速度由负转正即经过近日点
+      if approaching && position.dot(velocity) >= 0.0 {
+        periapses.push(position.z.atan2(position.x));
+      }
+    }
+    assert!(periapses.len() >= 3);
+    (periapses[periapses.len() - 1] - periapses[0]) / (periapses.len() - 1) as f32
+  }
+
+  #[test]
+  fn high_eccentricity_orbit_precesses_at_the_1pn_rate() {
+    let speed_of_light = 20.0;
+    // Δω = 6πμ / (c² a (1 - e²))，扣除积分器自身的进动
+    let expected = 6.0 * std::f32::consts::PI / (speed_of_light * speed_of_light * (1.0 - 0.6 * 0.6));
+    let measured = periapsis_advance(Some(speed_of_light)) - periapsis_advance(None);
+    assert!((measured - expected).abs() / expected < 0.05, "{} vs {}", measured, expected);
+  }
 }
@@ -0,0 +1,217 @@
+use std::fs;
+use std::path::Path;
+
+use bevy::math::DVec3;
+use serde::Deserialize;
+
+use super::scenario::{PlanetJson, Scenario};
+use super::units::UnitSystem;
+
+const BODY_TABLE_PATH: &str = "assets/json/horizons_bodies.json";
+const KILOMETER: f64 = 1000.0;
+const ASTRONOMICAL_UNIT_KM: f64 = 149_597_870.7;
+const DAY_SECONDS: f64 = 86_400.0;
+
+/// Horizons 天体编号对应的质量（kg）与半径（km）
+#[derive(Deserialize, Debug, Clone)]
+struct HorizonsBody {
+    id: i64,
+    name: String,
+    mass: f64,
+    radius: f64,
+    #[serde(default)]
+    star: bool,
+}
+
+/// 向量表第一条记录，位置单位 km，速度单位 km/s
+#[derive(Debug, Clone, PartialEq)]
+pub struct HorizonsVectors {
+    pub target: (String, Option<i64>),
+    pub center: (String, Option<i64>),
+    pub position: DVec3,
+    pub velocity: DVec3,
+}
+
+/// 导入本地保存的一组 Horizons 向量表（文件或包含 .txt 文件的目录），
+/// 各文件需使用同一个中心天体，结果换算到 `units` 单位制
+pub fn import(paths: &[&str], units: UnitSystem) -> Result<Scenario, String> {
+    let table = fs::read_to_string(BODY_TABLE_PATH).map_err(|err| format!("{}: {}", BODY_TABLE_PATH, err))?;
+    let table: Vec<HorizonsBody> = serde_json::from_str(&table).map_err(|err| format!("{}: {}", BODY_TABLE_PATH, err))?;
+    let mut files = Vec::new();
+    for path in paths {
+        let path = Path::new(path);
+        if path.is_dir() {
+            let mut entries: Vec<_> = fs::read_dir(path).map_err(|err| format!("{}: {}", path.display(), err))?
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|extension| extension == "txt"))
+                .collect();
+            entries.sort();
+            files.extend(entries);
+        } else {
+            files.push(path.to_path_buf());
+        }
+    }
+
+    let mut scenario = Scenario {
+        units,
+        ..Default::default()
+    };
+    let mut center = None;
+    for file in files {
+        let text = fs::read_to_string(&file).map_err(|err| format!("{}: {}", file.display(), err))?;
+        let vectors = parse_vector_table(&text).map_err(|err| format!("{}: {}", file.display(), err))?;
+        if center.get_or_insert_with(|| vectors.center.clone()) != &vectors.center {
+            return Err(format!("{}: center body {} differs from {}", file.display(), vectors.center.0, center.unwrap().0));
+        }
+        let body = find_body(&table, &vectors.target)
+            .ok_or_else(|| format!("{}: no mass and radius known for {}", file.display(), vectors.target.0))?;
+        push_body(&mut scenario, body, vectors.position, vectors.velocity);
+    }
+
+    // 日心坐标的导出文件里没有中心天体本身，补在原点
+    if let Some(center_body) = center.and_then(|center| find_body(&table, &center)) {
+        if scenario.find_body(&center_body.name).is_none() {
+            push_body(&mut scenario, center_body, DVec3::ZERO, DVec3::ZERO);
+        }
+    }
+    if scenario.fixed_stars.is_empty() && scenario.planets.is_empty() {
+        return Err("no Horizons vector tables found".to_string());
+    }
+    Ok(scenario)
+}
+
+fn find_body<'a>(table: &'a [HorizonsBody], (name, id): &(String, Option<i64>)) -> Option<&'a HorizonsBody> {
+    table.iter().find(|body| Some(body.id) == *id)
+        .or_else(|| table.iter().find(|body| body.name.eq_ignore_ascii_case(name)))
+}
+
+fn push_body(scenario: &mut Scenario, body: &HorizonsBody, position: DVec3, velocity: DVec3) {
+    let length = KILOMETER / scenario.units.length_in_meters();
+    let speed = length * scenario.units.time_in_seconds();
+    let planet = PlanetJson {
+        name: Some(body.name.clone()),
+        mass: (body.mass / scenario.units.mass_in_kilograms()) as f32,
+        position: (position * length).as_vec3().into(),
+        velocity: (velocity * speed).as_vec3().into(),
+        radius: (body.radius * length) as f32,
+        parent: None,
+        orbit: None,
+        spin: None,
+        model: None,
+        emissive: None,
+        light: None,
+    };
+    if body.star {
+        scenario.fixed_stars.push(planet);
+    } else {
+        scenario.planets.push(planet);
+    }
+}
+
+pub fn parse_vector_table(text: &str) -> Result<HorizonsVectors, String> {
+    let header_value = |key: &str| {
+        text.lines()
+            .find_map(|line| line.trim_start().strip_prefix(key))
+            .and_then(|rest| rest.trim_start().strip_prefix(':'))
+            .map(parse_body_name)
+    };
+    let target = header_value("Target body name").ok_or("missing target body name")?;
+    let center = header_value("Center body name").ok_or("missing center body name")?;
+    let (position_scale, velocity_scale) = match text.lines()
+        .find_map(|line| line.trim_start().strip_prefix("Output units"))
+        .and_then(|rest| rest.trim_start().strip_prefix(':'))
+        .map(str::trim)
+    {
+        None | Some("KM-S") => (1.0, 1.0),
+        Some("KM-D") => (1.0, 1.0 / DAY_SECONDS),
+        Some("AU-D") => (ASTRONOMICAL_UNIT_KM, ASTRONOMICAL_UNIT_KM / DAY_SECONDS),
+        Some(units) => return Err(format!("unsupported output units {}", units)),
+    };
+
+    let start = text.find("$$SOE").ok_or("missing $$SOE")? + "$$SOE".len();
+    let end = text.find("$$EOE").ok_or("missing $$EOE")?;
+    let records = text.get(start..end).ok_or("$$EOE before $$SOE")?;
+    let values = if records.contains(',') {
+        // CSV 格式：JDTDB, Calendar Date, X, Y, Z, VX, VY, VZ, ...
+        let record = records.lines().find(|line| !line.trim().is_empty()).ok_or("empty vector table")?;
+        let fields: Vec<&str> = record.split(',').map(str::trim).collect();
+        let parse = |index: usize| fields.get(index).and_then(|field| field.parse::<f64>().ok());
+        (2..8).map(parse).collect::<Option<Vec<f64>>>().ok_or("malformed CSV record")?
+    } else {
+        // 文本格式：历元行之后是 "X =... Y =... Z =..." 与 "VX=... VY=... VZ=..."
+        let record: String = records.lines().skip_while(|line| line.trim().is_empty()).skip(1)
+            .take_while(|line| line.starts_with(' '))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let mut record = record.replace(" =", "=");
+        while record.contains("= ") {
+            record = record.replace("= ", "=");
+        }
+        let value = |key: &str| record.split_whitespace()
+            .filter_map(|token| token.split_once('='))
+            .find(|(name, _)| *name == key)
+            .and_then(|(_, value)| value.parse::<f64>().ok());
+        ["X", "Y", "Z", "VX", "VY", "VZ"].into_iter().map(value).collect::<Option<Vec<f64>>>()
+            .ok_or("malformed vector record")?
+    };
+    Ok(HorizonsVectors {
+        target,
+        center,
+        position: to_simulation_frame(DVec3::new(values[0], values[1], values[2])) * position_scale,
+        velocity: to_simulation_frame(DVec3::new(values[3], values[4], values[5])) * velocity_scale,
+    })
+}
+
+// "Earth (399)" => ("Earth", Some(399))
+fn parse_body_name(value: &str) -> (String, Option<i64>) {
+    let value = value.split('{').next().unwrap_or_default().trim();
+    match value.rsplit_once('(') {
+        Some((name, id)) => (name.trim().to_string(), id.trim_end_matches(')').trim().parse().ok()),
+        None => (value.to_string(), None),
+    }
+}
+
+// 黄道坐标以 z 为北，映射为模拟中以 y 为法向的右手系
+fn to_simulation_frame(DVec3 { x, y, z }: DVec3) -> DVec3 {
+    DVec3::new(x, z, -y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    const EARTH: &str = "
+*******************************************************************************
+Target body name: Earth (399)                     {source: DE441}
+Center body name: Sun (10)                        {source: DE441}
+*******************************************************************************
+Output units    : KM-S
+*******************************************************************************
+$$SOE
+2460000.500000000 = A.D. 2023-Feb-24 00:00:00.0000 TDB
+ X =-1.390858035047413E+08 Y = 4.952718869002044E+07 Z =-3.497048004552378E+03
+ VX=-1.038013917468818E+01 VY=-2.794484545085017E+01 VZ= 1.722035838209599E-03
+ LT= 4.926026079702547E+02 RG= 1.476780720047106E+08 RR=-1.151089616493296E-01
+2460001.500000000 = A.D. 2023-Feb-25 00:00:00.0000 TDB
+ X =-1.399758035047413E+08 Y = 4.711718869002044E+07 Z =-3.347048004552378E+03
+ VX=-9.738013917468818E+00 VY=-2.814484545085017E+01 VZ= 1.822035838209599E-03
+ LT= 4.926026079702547E+02 RG= 1.476780720047106E+08 RR=-1.151089616493296E-01
+$$EOE
+";
+    #[test]
+    fn parses_first_text_record() {
+        let vectors = parse_vector_table(EARTH).unwrap();
+        assert_eq!(vectors.target, ("Earth".to_string(), Some(399)));
+        assert_eq!(vectors.center, ("Sun".to_string(), Some(10)));
+        assert_eq!(vectors.position, DVec3::new(-1.390858035047413E+08, -3.497048004552378E+03, -4.952718869002044E+07));
+        assert_eq!(vectors.velocity, DVec3::new(-1.038013917468818E+01, 1.722035838209599E-03, 2.794484545085017E+01));
+    }
+    #[test]
+    fn parses_csv_record() {
+        let csv = EARTH.replace(
+            &EARTH[EARTH.find("$$SOE").unwrap()..EARTH.find("$$EOE").unwrap()],
+            "$$SOE\n2460000.500000000, A.D. 2023-Feb-24 00:00:00.0000, -1.39E+08, 4.95E+07, -3.49E+03, -1.03E+01, -2.79E+01, 1.72E-03,\n",
+        );
+        let vectors = parse_vector_table(&csv).unwrap();
+        assert_eq!(vectors.position, DVec3::new(-1.39E+08, -3.49E+03, -4.95E+07));
+    }
+}
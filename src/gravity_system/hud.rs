@@ -0,0 +1,57 @@
+use bevy::prelude::*;
+
+use super::{escape::EscapeStats, motion::{MotionComp, SimulationTime}, units::UnitSystem};
+
+#[derive(Component)]
+struct HudText;
+
+pub struct HudPlugin;
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_hud);
+        app.add_systems(Update, update_hud);
+    }
+}
+
+fn spawn_hud(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 18.,
+                color: Color::WHITE,
+                ..default()
+            }
+        ).with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.),
+            left: Val::Px(10.),
+            ..default()
+        }),
+        HudText,
+    ));
+}
+
+fn update_hud(
+    mut query: Query<&mut Text, With<HudText>>,
+    units: Res<UnitSystem>,
+    simulation_time: Res<SimulationTime>,
+    bodies: Query<(), With<MotionComp>>,
+    escape_stats: Res<EscapeStats>,
+) {
+    let Ok(mut text) = query.get_single_mut() else { return; };
+    let (length, mass, time) = (units.length_unit(), units.mass_unit(), units.time_unit());
+    text.sections[0].value = format!(
+        "units: {}, {}, {}\nG = {:.4e} {}³/({}·{}²)\nt = {:.2} {}\nbodies: {}",
+        length, mass, time,
+        units.gravitational_constant(), length, mass, time,
+        simulation_time.elapsed, time,
+        bodies.iter().count(),
+    );
+    for escape in escape_stats.escapes.iter() {
+        text.sections[0].value += &format!(
+            "\nescaped: {} at t = {:.2} {}, v = {:.3} {}",
+            escape.name, escape.time, time, escape.velocity.length(), units.velocity_unit(),
+        );
+    }
+}
@@ -0,0 +1,166 @@
+use std::f32::consts::PI;
+
+use bevy::pbr::NotShadowCaster;
+use bevy::prelude::*;
+use bevy::scene::SceneInstanceReady;
+
+use super::{
+    collision_detection::CollisionDetection,
+    gravitation::GravitationComp,
+    planet::{FixedStar, SmallPlanet},
+    scenario::StarLightJson,
+    units::UnitSystem,
+};
+
+const SUN_TEMPERATURE: f32 = 5772.0;
+// 质量在此范围内（太阳质量）才按真实恒星推算色温
+const STELLAR_MASS_RANGE: std::ops::RangeInclusive<f64> = 0.08..=150.0;
+// 最亮恒星在最远行星处的照度
+const REFERENCE_ILLUMINANCE: f32 = 2000.0;
+const LIGHT_RANGE_FACTOR: f32 = 10.0;
+// 昼夜分界由恒星点光源的方向与阴影给出，环境光只保留一点，使夜面不至于全黑
+const NIGHT_SIDE_AMBIENT: f32 = 5.0;
+
+/// 恒星作为点光源的设置，由场景文件给出
+#[derive(Component, Debug, Clone, Copy)]
+pub struct StarLightSource(pub StarLightJson);
+
+/// 恒星的点光源子实体，恒星移除时随之移除
+#[derive(Component, Debug)]
+pub struct StarLight {
+    /// 生成时最远行星到恒星的距离，决定光强与照射范围
+    reference_distance: f32,
+}
+
+type ChangedStarFilter = (With<FixedStar>, Or<(Changed<GravitationComp>, Changed<Children>)>);
+
+pub struct LightingPlugin;
+impl Plugin for LightingPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(AmbientLight {
+            color: Color::WHITE,
+            brightness: NIGHT_SIDE_AMBIENT,
+        });
+        app.add_systems(Update, (attach_star_lights, update_star_lights).chain());
+        app.add_systems(Update, exclude_stars_from_shadows.run_if(on_event::<SceneInstanceReady>()));
+    }
+}
+
+fn attach_star_lights(
+    mut commands: Commands,
+    stars: Query<(Entity, &Transform, &CollisionDetection), Added<StarLightSource>>,
+    planets: Query<&Transform, With<SmallPlanet>>,
+) {
+    for (entity, transform, collision) in stars.iter() {
+        let reference_distance = planets.iter()
+            .map(|planet| planet.translation.distance(transform.translation))
+            .fold(0.0, f32::max);
+        // 没有行星时按恒星自身大小估计
+        let reference_distance = if reference_distance > 0.0 { reference_distance } else { collision.radius * 50.0 };
+        commands.entity(entity).with_children(|parent| {
+            // 行星背光面与被其他天体挡住的部分处于阴影中（食）
+            parent.spawn((
+                PointLightBundle {
+                    point_light: PointLight {
+                        shadows_enabled: true,
+                        ..default()
+                    },
+                    ..default()
+                },
+                StarLight { reference_distance },
+            ));
+        });
+    }
+}
+
+// 点光源位于恒星模型内部，恒星自身的网格不能投射阴影，否则会挡住全部光线
+fn exclude_stars_from_shadows(
+    mut commands: Commands,
+    mut ready_events: EventReader<SceneInstanceReady>,
+    stars: Query<(), With<FixedStar>>,
+    parents: Query<&Parent>,
+    children: Query<&Children>,
+    meshes: Query<(), With<Handle<Mesh>>>,
+) {
+    for event in ready_events.read() {
+        let is_star = stars.contains(event.parent)
+            || parents.get(event.parent).is_ok_and(|parent| stars.contains(parent.get()));
+        if !is_star { continue; }
+        for entity in children.iter_descendants(event.parent) {
+            if meshes.contains(entity) {
+                commands.entity(entity).insert(NotShadowCaster);
+            }
+        }
+    }
+}
+
+// 恒星增减或质量变化（如合并）后重新计算所有恒星的光强与颜色
+fn update_star_lights(
+    stars: Query<(&GravitationComp, &StarLightSource, &Children), With<FixedStar>>,
+    changed: Query<(), ChangedStarFilter>,
+    mut removed: RemovedComponents<StarLightSource>,
+    mut lights: Query<(&mut PointLight, &StarLight)>,
+    units: Res<UnitSystem>,
+) {
+    if changed.is_empty() && removed.read().count() == 0 { return; }
+    let heaviest = stars.iter().map(|(gravitation, ..)| gravitation.mass).fold(0.0, f32::max);
+    if heaviest <= 0.0 { return; }
+    for (gravitation, StarLightSource(settings), children) in stars.iter() {
+        let mass_ratio = gravitation.mass / heaviest;
+        let solar_mass = units.in_solar_masses(gravitation.mass);
+        let temperature_mass = if STELLAR_MASS_RANGE.contains(&solar_mass) { solar_mass as f32 } else { mass_ratio };
+        let color = settings.color
+            .map(|[red, green, blue]| Color::linear_rgb(red, green, blue))
+            .unwrap_or_else(|| blackbody_color(SUN_TEMPERATURE * temperature_mass.sqrt()));
+        // 主序星质光关系 L ∝ M^3.5
+        let luminosity = settings.luminosity.unwrap_or_else(|| mass_ratio.powf(3.5));
+        for child in children.iter() {
+            let Ok((mut light, star_light)) = lights.get_mut(*child) else { continue; };
+            let distance = star_light.reference_distance;
+            light.color = color;
+            light.intensity = 4.0 * PI * distance.powi(2) * REFERENCE_ILLUMINANCE * luminosity;
+            light.range = distance * LIGHT_RANGE_FACTOR;
+        }
+    }
+}
+
+/// 黑体辐射颜色的近似拟合，适用于 1000 K 到 40000 K
+pub fn blackbody_color(temperature: f32) -> Color {
+    let temperature = temperature.clamp(1000.0, 40000.0) / 100.0;
+    let red = if temperature <= 66.0 {
+        255.0
+    } else {
+        329.699 * (temperature - 60.0).powf(-0.133_204_76)
+    };
+    let green = if temperature <= 66.0 {
+        99.470_8 * temperature.ln() - 161.119_57
+    } else {
+        288.122_16 * (temperature - 60.0).powf(-0.075_514_846)
+    };
+    let blue = if temperature >= 66.0 {
+        255.0
+    } else if temperature <= 19.0 {
+        0.0
+    } else {
+        138.517_73 * (temperature - 10.0).ln() - 305.044_8
+    };
+    Color::srgb_u8(
+        red.clamp(0.0, 255.0) as u8,
+        green.clamp(0.0, 255.0) as u8,
+        blue.clamp(0.0, 255.0) as u8,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn blackbody_color_shifts_from_red_to_blue() {
+        let cool = blackbody_color(3000.0).to_srgba();
+        let sun = blackbody_color(SUN_TEMPERATURE).to_srgba();
+        let hot = blackbody_color(20000.0).to_srgba();
+        assert!(cool.red > cool.blue);
+        assert!(sun.red > 0.95 && sun.green > 0.85);
+        assert!(hot.blue > hot.red);
+    }
+}
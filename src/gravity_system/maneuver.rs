@@ -0,0 +1,561 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_egui::{egui, EguiContexts};
+use serde::{Deserialize, Serialize};
+
+use crate::loading_state::AppState;
+
+use super::{
+    collision_detection::CollisionDetection,
+    gravitation::{gravitational_acceleration, GravitationComp},
+    motion::{BodyId, MotionComp, SimulationTime},
+    particle::TestParticle,
+    replay::{replaying, tick_allowed},
+    running_state::{ResetEvent, RunningState},
+    ship::{apply_ship_controls, dominant_attractor, orbit_frame, ship_active, OrbitFrame, PlayerShip},
+    units::UnitSystem,
+    GravityStatusUpdateSet,
+};
+
+// 预测轨迹每隔多少步保留一个点，以及预测步数的上限
+const PATH_STRIDE: usize = 4;
+const MAX_PREDICTION_STEPS: usize = 20000;
+// 手柄长度为到主导引力源距离的比例；拖动一个手柄长度改变的 Δv 占剩余预算的比例；拾取半径（像素）
+const HANDLE_LENGTH: f32 = 0.1;
+const HANDLE_DELTA_V: f32 = 0.1;
+const HANDLE_PICK_RADIUS: f32 = 10.0;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BurnMode {
+    Impulse,
+    /// 以最大推力点火，点火过程以节点时刻为中点
+    Finite,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct ManeuverNode {
+    /// 执行时的模拟时间
+    pub time: f64,
+    /// 沿 (顺行, 法向, 径向朝外) 的 Δv 分量
+    pub delta_v: [f32; 3],
+    pub mode: BurnMode,
+}
+
+impl ManeuverNode {
+    pub fn world_delta_v(&self, frame: &OrbitFrame) -> Vec3 {
+        frame.prograde * self.delta_v[0] + frame.normal * self.delta_v[1] + frame.radial * self.delta_v[2]
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ActiveBurn {
+    direction: Vec3,
+    remaining: f32,
+}
+
+/// 飞船的机动计划，节点按时间排序
+#[derive(Resource, Debug, Default, Clone)]
+pub struct ManeuverPlan {
+    pub nodes: Vec<ManeuverNode>,
+    burn: Option<ActiveBurn>,
+}
+
+/// 一个固定步内机动产生的速度突变与推力加速度
+#[derive(Debug, Default)]
+pub struct ManeuverStep {
+    pub impulse: Vec3,
+    pub acceleration: Vec3,
+    /// 本步开始执行的节点所用的轨道坐标系
+    pub executed: Option<OrbitFrame>,
+}
+
+impl ManeuverPlan {
+    /// 在固定步开始时调用，实时模拟与轨迹预测共用。
+    /// `offset` 与 `relative_velocity` 相对主导引力源，轨道面不确定时到时的节点被丢弃
+    pub fn step(&mut self, ship: &mut PlayerShip, time: f64, dt: f32, offset: Vec3, relative_velocity: Vec3) -> ManeuverStep {
+        let mut result = ManeuverStep::default();
+        if let (None, Some(node)) = (self.burn, self.nodes.first().copied()) {
+            let magnitude = Vec3::from(node.delta_v).length();
+            let lead = match node.mode {
+                BurnMode::Impulse => 0.0,
+                BurnMode::Finite => (magnitude * ship.mass() / ship.max_thrust) as f64 / 2.0,
+            };
+            if node.time - lead <= time {
+                self.nodes.remove(0);
+                if let Some(frame) = orbit_frame(offset, relative_velocity) {
+                    let direction = node.world_delta_v(&frame).normalize_or_zero();
+                    match node.mode {
+                        BurnMode::Impulse => result.impulse = direction * ship.impulse(magnitude),
+                        BurnMode::Finite => self.burn = Some(ActiveBurn { direction, remaining: magnitude }),
+                    }
+                    result.executed = Some(frame);
+                }
+            }
+        }
+        if let Some(burn) = &mut self.burn {
+            let thrust = ship.max_thrust.min(burn.remaining / dt * ship.mass());
+            let acceleration = ship.burn(thrust, dt);
+            burn.remaining -= acceleration * dt;
+            result.acceleration = burn.direction * acceleration;
+            if burn.remaining <= 0.0 || acceleration <= 0.0 {
+                self.burn = None;
+            }
+        }
+        result
+    }
+}
+
+/// 用户在面板中修改后的全部节点，在 PostUpdate 中替换计划
+#[derive(Event, Debug, Clone)]
+pub struct ManeuverEditEvent {
+    pub nodes: Vec<ManeuverNode>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PredictionBody {
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub mass: f32,
+    pub radius: f32,
+}
+
+/// 进入另一天体主导范围后的最近点
+#[derive(Debug, Clone, Copy)]
+pub struct Encounter {
+    pub body: usize,
+    pub time: f64,
+    pub position: Vec3,
+    pub body_position: Vec3,
+    pub distance: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PredictedNode {
+    /// 在预测开始时计划中的序号；轨道面不确定而被丢弃的节点没有对应的预测
+    pub node: usize,
+    pub position: Vec3,
+    pub frame: OrbitFrame,
+    /// 到主导引力源的距离，用于缩放手柄
+    pub distance: f32,
+}
+
+impl PredictedNode {
+    /// 顺行、法向、径向三个手柄末端的位置
+    pub fn handle(&self, axis: usize) -> Vec3 {
+        let direction = [self.frame.prograde, self.frame.normal, self.frame.radial][axis];
+        self.position + direction * self.distance * HANDLE_LENGTH
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Prediction {
+    pub path: Vec<Vec3>,
+    pub nodes: Vec<PredictedNode>,
+    pub encounters: Vec<Encounter>,
+    pub impact: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PredictionSettings {
+    pub start: f64,
+    pub dt: f32,
+    pub steps: usize,
+    pub gravitational_constant: f32,
+}
+
+/// 与实时模拟相同的半隐式欧拉积分，引力源按传入顺序求和，调用方应按 BodyId 排好序
+pub fn predict(
+    mut bodies: Vec<PredictionBody>,
+    subject: usize,
+    mut ship: PlayerShip,
+    mut plan: ManeuverPlan,
+    settings: PredictionSettings,
+) -> Prediction {
+    let PredictionSettings { start, dt, steps, gravitational_constant } = settings;
+    let mut prediction = Prediction::default();
+    let dominant = |bodies: &[PredictionBody]| {
+        let position = bodies[subject].position;
+        dominant_attractor(position, bodies.iter().enumerate()
+            .map(|(index, body)| (body.position, if index == subject { 0.0 } else { body.mass })))
+    };
+    let home = dominant(&bodies);
+    let node_count = plan.nodes.len();
+    let mut last_distance = vec![f32::INFINITY; bodies.len()];
+    for step in 0..steps {
+        let time = start + step as f64 * dt as f64;
+        let mut accelerations: Vec<Vec3> = bodies.iter().enumerate().map(|(index, body)| {
+            bodies.iter().enumerate()
+                .filter(|(other, attractor)| *other != index && attractor.mass > 0.0)
+                .map(|(_, attractor)| gravitational_acceleration(body.position, attractor.position, gravitational_constant * attractor.mass))
+                .sum()
+        }).collect();
+        let attractor = dominant(&bodies);
+        let (offset, relative_velocity) = attractor.map_or((bodies[subject].position, bodies[subject].velocity), |index| {
+            (bodies[subject].position - bodies[index].position, bodies[subject].velocity - bodies[index].velocity)
+        });
+        // 本步若执行了节点，它就是尚未执行的第一个
+        let node = node_count - plan.nodes.len();
+        let maneuver = plan.step(&mut ship, time, dt, offset, relative_velocity);
+        if let Some(frame) = maneuver.executed {
+            prediction.nodes.push(PredictedNode { node, position: bodies[subject].position, frame, distance: offset.length() });
+        }
+        bodies[subject].mass = ship.mass();
+        bodies[subject].velocity += maneuver.impulse;
+        accelerations[subject] += maneuver.acceleration;
+        for (body, acceleration) in bodies.iter_mut().zip(accelerations) {
+            body.velocity += acceleration * dt;
+            body.position += body.velocity * dt;
+        }
+        let ship_body = bodies[subject];
+        if step % PATH_STRIDE == 0 {
+            prediction.path.push(ship_body.position);
+        }
+        for (index, body) in bodies.iter().enumerate() {
+            if index == subject { continue; }
+            let distance = ship_body.position.distance(body.position);
+            if distance < ship_body.radius + body.radius {
+                prediction.impact = Some(index);
+            }
+            // 距离由减转增时即为最近点，出发时所绕的天体不算
+            let receding = distance > last_distance[index];
+            last_distance[index] = distance;
+            if receding && Some(index) == attractor && attractor != home
+                && prediction.encounters.iter().all(|encounter| encounter.body != index) {
+                prediction.encounters.push(Encounter { body: index, time, position: ship_body.position, body_position: body.position, distance });
+            }
+        }
+        if prediction.impact.is_some() {
+            prediction.path.push(ship_body.position);
+            break;
+        }
+    }
+    prediction
+}
+
+/// 正在拖动的手柄，`time` 用于确认拖动期间节点没有被执行或重新排序
+#[derive(Debug, Clone, Copy)]
+struct HandleDrag {
+    node: usize,
+    time: f64,
+    axis: usize,
+    cursor: Vec2,
+}
+
+/// 当前的预测结果，每帧从实时状态重新计算
+#[derive(Resource, Debug)]
+pub struct ManeuverPreview {
+    pub steps: usize,
+    dt: f32,
+    prediction: Prediction,
+    names: Vec<String>,
+    /// 飞船剩余的 Δv，决定拖动手柄的灵敏度
+    budget: f32,
+    dragging: Option<HandleDrag>,
+}
+
+impl Default for ManeuverPreview {
+    fn default() -> Self {
+        Self {
+            steps: 3000,
+            dt: 0.0,
+            prediction: Prediction::default(),
+            names: Vec::new(),
+            budget: 0.0,
+            dragging: None,
+        }
+    }
+}
+
+type PredictionQuery<'a> = (
+    &'a BodyId,
+    &'a Transform,
+    &'a MotionComp,
+    &'a GravitationComp,
+    Option<&'a CollisionDetection>,
+    Option<&'a Name>,
+    Option<&'a PlayerShip>,
+);
+type ManeuverShipQuery<'a> = (&'a Transform, &'a mut MotionComp, &'a mut GravitationComp, &'a mut PlayerShip);
+type AttractorFilter = (Without<PlayerShip>, Without<TestParticle>);
+
+pub struct ManeuverPlugin;
+impl Plugin for ManeuverPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ManeuverPlan>();
+        app.init_resource::<ManeuverPreview>();
+        app.add_event::<ManeuverEditEvent>();
+        app.add_systems(FixedUpdate, execute_maneuvers
+            .after(apply_ship_controls)
+            .before(GravityStatusUpdateSet::VelocityUpdate)
+            .run_if(in_state(RunningState::Running).and_then(tick_allowed)));
+        app.add_systems(PostUpdate, (clear_plan.run_if(on_event::<ResetEvent>()), apply_edits).chain());
+        app.add_systems(Update, (maneuver_panel.run_if(not(replaying)), update_preview, drag_handles.run_if(not(replaying)), draw_preview).chain()
+            .run_if(in_state(AppState::Running).and_then(ship_active)));
+    }
+}
+
+fn execute_maneuvers(
+    mut ships: Query<ManeuverShipQuery>,
+    bodies: Query<(&Transform, &MotionComp, &GravitationComp), AttractorFilter>,
+    mut plan: ResMut<ManeuverPlan>,
+    simulation_time: Res<SimulationTime>,
+    time: Res<Time>,
+) {
+    let Ok((transform, mut motion, mut gravitation, mut ship)) = ships.get_single_mut() else { return; };
+    if plan.nodes.is_empty() && plan.burn.is_none() { return; }
+    let attractors: Vec<(Vec3, Vec3, f32)> = bodies.iter()
+        .map(|(transform, motion, gravitation)| (transform.translation, motion.velocity, gravitation.mass))
+        .collect();
+    let position = transform.translation;
+    let attractor = dominant_attractor(position, attractors.iter().map(|(position, _, mass)| (*position, *mass)));
+    let (offset, relative_velocity) = attractor.map_or((position, motion.velocity), |index| {
+        (position - attractors[index].0, motion.velocity - attractors[index].1)
+    });
+    let maneuver = plan.step(&mut ship, simulation_time.elapsed, time.delta_seconds(), offset, relative_velocity);
+    gravitation.mass = ship.mass();
+    motion.velocity += maneuver.impulse;
+    motion.acceleration += maneuver.acceleration;
+}
+
+fn clear_plan(mut plan: ResMut<ManeuverPlan>) {
+    *plan = ManeuverPlan::default();
+}
+
+fn apply_edits(mut edit_events: EventReader<ManeuverEditEvent>, mut plan: ResMut<ManeuverPlan>) {
+    for event in edit_events.read() {
+        plan.nodes = event.nodes.clone();
+        plan.nodes.sort_by(|a, b| a.time.total_cmp(&b.time));
+    }
+}
+
+fn update_preview(
+    bodies: Query<PredictionQuery, Without<TestParticle>>,
+    plan: Res<ManeuverPlan>,
+    mut preview: ResMut<ManeuverPreview>,
+    simulation_time: Res<SimulationTime>,
+    fixed_time: Res<Time<Fixed>>,
+    units: Res<UnitSystem>,
+) {
+    let mut bodies: Vec<_> = bodies.iter().collect();
+    bodies.sort_by_key(|(id, ..)| **id);
+    let Some(subject) = bodies.iter().position(|(.., ship)| ship.is_some()) else { return; };
+    let Some(ship) = bodies[subject].6.copied() else { return; };
+    preview.names = bodies.iter()
+        .map(|(id, .., name, _)| name.map_or_else(|| format!("body {}", id.0), |name| name.to_string()))
+        .collect();
+    let bodies = bodies.iter().map(|(_, transform, motion, gravitation, collision, ..)| PredictionBody {
+        position: transform.translation,
+        velocity: motion.velocity,
+        mass: gravitation.mass,
+        radius: collision.map_or(0.0, |collision| collision.radius),
+    }).collect();
+    preview.budget = ship.delta_v_budget();
+    preview.dt = fixed_time.timestep().as_secs_f32();
+    preview.steps = preview.steps.min(MAX_PREDICTION_STEPS);
+    preview.prediction = predict(bodies, subject, ship, plan.clone(), PredictionSettings {
+        start: simulation_time.elapsed,
+        dt: preview.dt,
+        steps: preview.steps,
+        gravitational_constant: units.gravitational_constant(),
+    });
+}
+
+/// 光标沿手柄在屏幕上的投影移动的距离，以手柄在屏幕上的长度为单位
+fn drag_along(screen_handle: Vec2, drag: Vec2) -> f32 {
+    if screen_handle.length_squared() <= f32::EPSILON { return 0.0; }
+    drag.dot(screen_handle) / screen_handle.length_squared()
+}
+
+// 按下左键时拾取最近的手柄，按住拖动时沿手柄方向改变对应的 Δv 分量，反向拖动可减到负值
+fn drag_handles(
+    mouse: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    mut contexts: EguiContexts,
+    mut preview: ResMut<ManeuverPreview>,
+    plan: Res<ManeuverPlan>,
+    mut edit_event_writer: EventWriter<ManeuverEditEvent>,
+) {
+    if !mouse.pressed(MouseButton::Left) {
+        preview.dragging = None;
+        return;
+    }
+    let Some(cursor) = windows.get_single().ok().and_then(Window::cursor_position) else { return; };
+    let Ok((camera, camera_transform)) = cameras.get_single() else { return; };
+    let project = |position: Vec3| camera.world_to_viewport(camera_transform, position);
+    if mouse.just_pressed(MouseButton::Left) {
+        if contexts.ctx_mut().is_pointer_over_area() { return; }
+        preview.dragging = preview.prediction.nodes.iter()
+            .flat_map(|predicted| (0..3).map(move |axis| (predicted, axis)))
+            .filter_map(|(predicted, axis)| {
+                let distance = project(predicted.handle(axis))?.distance(cursor);
+                let node = plan.nodes.get(predicted.node)?;
+                (distance <= HANDLE_PICK_RADIUS).then_some((HandleDrag { node: predicted.node, time: node.time, axis, cursor }, distance))
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(drag, _)| drag);
+        return;
+    }
+    let Some(drag) = preview.dragging else { return; };
+    let predicted = preview.prediction.nodes.iter().find(|predicted| predicted.node == drag.node);
+    let Some((origin, tip)) = predicted.and_then(|predicted| Some((project(predicted.position)?, project(predicted.handle(drag.axis))?))) else { return; };
+    let mut nodes = plan.nodes.clone();
+    let Some(node) = nodes.get_mut(drag.node).filter(|node| node.time == drag.time) else {
+        preview.dragging = None;
+        return;
+    };
+    let handles = drag_along(tip - origin, cursor - drag.cursor);
+    if handles == 0.0 { return; }
+    node.delta_v[drag.axis] += handles * preview.budget * HANDLE_DELTA_V;
+    preview.dragging = Some(HandleDrag { cursor, ..drag });
+    edit_event_writer.send(ManeuverEditEvent { nodes });
+}
+
+// 手柄末端的小球可拖动；箭头长度以手柄长度对应最大的分量
+fn draw_preview(mut gizmos: Gizmos, preview: Res<ManeuverPreview>, plan: Res<ManeuverPlan>) {
+    let prediction = &preview.prediction;
+    gizmos.linestrip(prediction.path.iter().copied(), Color::srgb(0.3, 0.8, 1.0));
+    let colors = [Color::srgb(0.9, 0.85, 0.2), Color::srgb(0.85, 0.3, 0.85), Color::srgb(0.3, 0.85, 0.9)];
+    for predicted in prediction.nodes.iter() {
+        let Some(node) = plan.nodes.get(predicted.node) else { continue; };
+        let largest = node.delta_v.iter().fold(f32::EPSILON, |largest, component| largest.max(component.abs()));
+        let length = predicted.distance * HANDLE_LENGTH;
+        gizmos.sphere(predicted.position, Quat::IDENTITY, predicted.distance * 0.01, Color::WHITE);
+        for (axis, color) in colors.into_iter().enumerate() {
+            let handle = predicted.handle(axis);
+            let direction = (handle - predicted.position) / length;
+            if node.delta_v[axis] != 0.0 {
+                gizmos.arrow(predicted.position, predicted.position + direction * node.delta_v[axis] / largest * length, color);
+            }
+            let dragged = preview.dragging.is_some_and(|drag| drag.node == predicted.node && drag.axis == axis);
+            gizmos.line(predicted.position, handle, color.with_alpha(0.3));
+            gizmos.sphere(handle, Quat::IDENTITY, length * if dragged { 0.12 } else { 0.06 }, color);
+        }
+    }
+    for encounter in prediction.encounters.iter() {
+        let color = Color::srgb(1.0, 0.5, 0.1);
+        gizmos.sphere(encounter.position, Quat::IDENTITY, encounter.distance * 0.05, color);
+        gizmos.line(encounter.position, encounter.body_position, color);
+    }
+}
+
+fn maneuver_panel(
+    mut contexts: EguiContexts,
+    plan: Res<ManeuverPlan>,
+    mut preview: ResMut<ManeuverPreview>,
+    ships: Query<&PlayerShip>,
+    simulation_time: Res<SimulationTime>,
+    units: Res<UnitSystem>,
+    mut edit_event_writer: EventWriter<ManeuverEditEvent>,
+) {
+    let Ok(ship) = ships.get_single() else { return; };
+    let mut nodes = plan.nodes.clone();
+    let mut changed = false;
+    egui::Window::new("Maneuver").default_width(300.0).show(contexts.ctx_mut(), |ui| {
+        ui.horizontal(|ui| {
+            ui.label("prediction steps");
+            ui.add(egui::DragValue::new(&mut preview.steps).range(100..=MAX_PREDICTION_STEPS));
+        });
+        ui.label(format!("Δv budget: {:.3} {}", ship.delta_v_budget(), units.velocity_unit()));
+        let time_speed = preview.dt as f64 * 10.0;
+        let mut removed = None;
+        for (index, node) in nodes.iter_mut().enumerate() {
+            ui.separator();
+            ui.push_id(index, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(format!("node {} at", index + 1));
+                    changed |= ui.add(egui::DragValue::new(&mut node.time).speed(time_speed)).changed();
+                    ui.label(units.time_unit());
+                });
+                ui.horizontal(|ui| {
+                    for (component, label) in node.delta_v.iter_mut().zip(["pro ", "nrm ", "rad "]) {
+                        changed |= ui.add(egui::DragValue::new(component).speed(0.01).prefix(label)).changed();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    let mut finite = node.mode == BurnMode::Finite;
+                    if ui.checkbox(&mut finite, "finite burn").changed() {
+                        node.mode = if finite { BurnMode::Finite } else { BurnMode::Impulse };
+                        changed = true;
+                    }
+                    if ui.button("Remove").clicked() {
+                        removed = Some(index);
+                    }
+                });
+            });
+        }
+        if let Some(index) = removed {
+            nodes.remove(index);
+            changed = true;
+        }
+        ui.separator();
+        if ui.button("Add node").clicked() {
+            let horizon = preview.dt as f64 * preview.steps as f64;
+            nodes.push(ManeuverNode { time: simulation_time.elapsed + horizon / 4.0, delta_v: [0.0; 3], mode: BurnMode::Impulse });
+            changed = true;
+        }
+        let prediction = &preview.prediction;
+        for encounter in prediction.encounters.iter() {
+            ui.label(format!(
+                "encounter {} at {:.2} {}, {:.3} {}",
+                preview.names[encounter.body], encounter.time, units.time_unit(), encounter.distance, units.length_unit(),
+            ));
+        }
+        if let Some(index) = prediction.impact {
+            ui.colored_label(egui::Color32::RED, format!("impact with {}", preview.names[index]));
+        }
+    });
+    if changed {
+        edit_event_writer.send(ManeuverEditEvent { nodes });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn prograde_impulse_raises_apoapsis_as_vis_viva_predicts() {
+        let bodies = vec![
+            PredictionBody { position: Vec3::ZERO, velocity: Vec3::ZERO, mass: 1.0e7, radius: 1.0 },
+            PredictionBody { position: Vec3::X * 10.0, velocity: Vec3::Z * 10.0, mass: 1.0e-3, radius: 0.1 },
+        ];
+        let ship = PlayerShip { dry_mass: 1.0e-3, fuel: 1.0e-3, exhaust_velocity: 10.0, max_thrust: 1.0 };
+        let plan = ManeuverPlan {
+            nodes: vec![ManeuverNode { time: 0.0, delta_v: [1.0, 0.0, 0.0], mode: BurnMode::Impulse }],
+            burn: None,
+        };
+        let settings = PredictionSettings { start: 0.0, dt: 1.0e-3, steps: 20000, gravitational_constant: 1.0e-4 };
+        let prediction = predict(bodies, 1, ship, plan, settings);
+        assert_eq!(prediction.nodes.len(), 1);
+        assert!(prediction.impact.is_none());
+        // μ = 1000，r = 10，v = 11：a = 1 / (2 / r - v² / μ)，远拱点 2a - r
+        let apoapsis = 2.0 / (2.0 / 10.0 - 121.0 / 1000.0) - 10.0;
+        let reached = prediction.path.iter().map(|position| position.length()).fold(0.0, f32::max);
+        assert!((reached - apoapsis).abs() / apoapsis < 0.02, "{} vs {}", reached, apoapsis);
+    }
+    #[test]
+    fn dropped_node_does_not_shift_later_indices() {
+        // 飞船径直远离主天体，第一个节点时轨道面不确定被丢弃，旁边的小天体随后使速度偏转
+        let bodies = vec![
+            PredictionBody { position: Vec3::ZERO, velocity: Vec3::ZERO, mass: 1.0e7, radius: 1.0 },
+            PredictionBody { position: Vec3::X * 10.0, velocity: Vec3::X, mass: 1.0e-3, radius: 0.1 },
+            PredictionBody { position: Vec3::new(10.0, 5.0, 0.0), velocity: Vec3::ZERO, mass: 1.0e3, radius: 0.1 },
+        ];
+        let ship = PlayerShip { dry_mass: 1.0e-3, fuel: 1.0e-3, exhaust_velocity: 10.0, max_thrust: 1.0 };
+        let node = |time| ManeuverNode { time, delta_v: [0.1, 0.0, 0.0], mode: BurnMode::Impulse };
+        let plan = ManeuverPlan { nodes: vec![node(0.0), node(0.01)], burn: None };
+        let settings = PredictionSettings { start: 0.0, dt: 1.0e-3, steps: 100, gravitational_constant: 1.0e-4 };
+        let prediction = predict(bodies, 1, ship, plan, settings);
+        assert_eq!(prediction.nodes.len(), 1);
+        assert_eq!(prediction.nodes[0].node, 1);
+    }
+    #[test]
+    fn dragging_along_handle_counts_handle_lengths() {
+        let handle = Vec2::new(30.0, 40.0);
+        assert!((drag_along(handle, Vec2::new(15.0, 20.0)) - 0.5).abs() < 1e-6);
+        assert!((drag_along(handle, Vec2::new(-40.0, 30.0))).abs() < 1e-6);
+        assert!((drag_along(handle, -handle) + 1.0).abs() < 1e-6);
+        assert_eq!(drag_along(Vec2::ZERO, handle), 0.0);
+    }
+}
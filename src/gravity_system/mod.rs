@@ -6,6 +6,23 @@ use camera::CameraPlugin;
 use running_state::{RunningState, RunningStatePlugin};
 use debugger::DebuggerPlugin;
 use collision_detection::CollisionDetectionPlugin;
+use particle::ParticlePlugin;
+use generator::GeneratorPlugin;
+use hud::HudPlugin;
+use tides::TidesPlugin;
+use eclipse::EclipsePlugin;
+use escape::EscapePlugin;
+use event_log::EventLogPlugin;
+use orbit_events::OrbitEventsPlugin;
+use rewind::RewindPlugin;
+use replay::ReplayPlugin;
+use plot::PlotPlugin;
+use puzzle::PuzzlePlugin;
+use ship::ShipPlugin;
+use maneuver::ManeuverPlugin;
+use timeline::TimelinePlugin;
+use trajectory::TrajectoryPlugin;
+use lighting::LightingPlugin;
 
 mod gravitation;
 mod motion;
@@ -14,6 +31,27 @@ mod camera;
 mod running_state;
 mod debugger;
 mod collision_detection;
+mod eclipse;
+mod escape;
+mod event_log;
+mod generator;
+mod horizons;
+mod hud;
+mod lighting;
+mod maneuver;
+mod orbit;
+mod orbit_events;
+mod particle;
+mod plot;
+mod puzzle;
+mod replay;
+mod rewind;
+mod scenario;
+mod ship;
+mod tides;
+mod timeline;
+mod trajectory;
+mod units;
 
 pub struct GravitySystemPlugin;
 impl Plugin for GravitySystemPlugin {
@@ -26,13 +64,32 @@ impl Plugin for GravitySystemPlugin {
                     GravityStatusUpdateSet::VelocityUpdate,
                     GravityStatusUpdateSet::CollisionDetection,
                     GravityStatusUpdateSet::PositionUpdate,
+                    GravityStatusUpdateSet::Analysis,
+                    GravityStatusUpdateSet::Record,
                 ).chain()
-                .run_if(in_state(RunningState::Running))
+                .run_if(in_state(RunningState::Running).and_then(replay::tick_allowed))
             )
             .add_plugins(RunningStatePlugin)
             .add_plugins(CollisionDetectionPlugin)
             .add_plugins(DebuggerPlugin)
             .add_plugins(PlanetPlugin)
+            .add_plugins(ReplayPlugin)
+            .add_plugins(ParticlePlugin)
+            .add_plugins(GeneratorPlugin)
+            .add_plugins(HudPlugin)
+            .add_plugins(TidesPlugin)
+            .add_plugins(EscapePlugin)
+            .add_plugins(EclipsePlugin)
+            .add_plugins(OrbitEventsPlugin)
+            .add_plugins(EventLogPlugin)
+            .add_plugins(RewindPlugin)
+            .add_plugins(PlotPlugin)
+            .add_plugins(PuzzlePlugin)
+            .add_plugins(ShipPlugin)
+            .add_plugins(ManeuverPlugin)
+            .add_plugins(TimelinePlugin)
+            .add_plugins(TrajectoryPlugin)
+            .add_plugins(LightingPlugin)
             .add_plugins(MotionPlugin)
             .add_plugins(GravitationPlugin)
             .add_plugins(CameraPlugin);
@@ -46,4 +103,8 @@ pub enum GravityStatusUpdateSet {
     VelocityUpdate,
     CollisionDetection,
     PositionUpdate,
+    /// 位置更新后对本步状态做统计与判定
+    Analysis,
+    /// 本步结束后的快照与校验和
+    Record,
 }
\ No newline at end of file
@@ -1,34 +1,52 @@
-use std::borrow::BorrowMut;
-
 use bevy::prelude::*;
 
-use super::GravityStatusUpdateSet;
+use super::{running_state::ResetEvent, GravityStatusUpdateSet};
 
 
 #[derive(Component)]
 pub struct MotionComp {
     pub velocity: Vec3,
     pub acceleration: Vec3,
-    pub self_rotation: Quat,
+    /// 自转角速度，方向为自转轴，大小为每单位时间转过的弧度
+    pub angular_velocity: Vec3,
 }
+// 不能使用全局随机数，否则回放无法复现
 impl Default for MotionComp {
     fn default() -> Self {
         Self {
             velocity: Vec3::ZERO,
             acceleration: Vec3::ZERO,
-            self_rotation: Quat::from_rng(rand::thread_rng().borrow_mut())
+            angular_velocity: Vec3::ZERO,
         }
     }
 }
 
+/// 天体的稳定编号，重置前不会复用；回退时用它对应快照与实体
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BodyId(pub u64);
+
+#[derive(Resource, Debug, Default)]
+struct NextBodyId(u64);
+
+/// 模拟经过的时间（以场景单位制的时间单位计）和固定步数
+#[derive(Resource, Debug, Default)]
+pub struct SimulationTime {
+    pub elapsed: f64,
+    pub tick: u64,
+}
+
 pub struct MotionPlugin;
 impl Plugin for MotionPlugin {
     fn build(&self, app: &mut App) {
         app
+            .init_resource::<SimulationTime>()
+            .init_resource::<NextBodyId>()
+            .add_systems(FixedUpdate, assign_body_ids.before(GravityStatusUpdateSet::AccelerationUpdate))
             .add_systems(FixedUpdate,
                 velocity_update.chain().in_set(GravityStatusUpdateSet::VelocityUpdate))
             .add_systems(FixedUpdate,
-                position_update.chain().in_set(GravityStatusUpdateSet::PositionUpdate));
+                (position_update, rotation_update, advance_simulation_time).chain().in_set(GravityStatusUpdateSet::PositionUpdate))
+            .add_systems(PostUpdate, (reset_simulation_time, reset_body_ids).run_if(on_event::<ResetEvent>()));
     }
 }
 
@@ -45,3 +63,34 @@ fn position_update(mut query: Query<(&mut Transform, &mut MotionComp)>, time: Re
         transform.translation += motion.velocity * time.delta_seconds();
     }
 }
+
+fn rotation_update(mut query: Query<(&mut Transform, &MotionComp)>, time: Res<Time>) {
+    for (mut transform, motion) in query.iter_mut() {
+        if motion.angular_velocity == Vec3::ZERO { continue; }
+        transform.rotation = (Quat::from_scaled_axis(motion.angular_velocity * time.delta_seconds()) * transform.rotation).normalize();
+    }
+}
+
+fn advance_simulation_time(mut simulation_time: ResMut<SimulationTime>, time: Res<Time>) {
+    simulation_time.elapsed += time.delta_seconds_f64();
+    simulation_time.tick += 1;
+}
+
+fn assign_body_ids(
+    mut commands: Commands,
+    query: Query<Entity, (With<MotionComp>, Without<BodyId>)>,
+    mut next_id: ResMut<NextBodyId>,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).insert(BodyId(next_id.0));
+        next_id.0 += 1;
+    }
+}
+
+fn reset_body_ids(mut next_id: ResMut<NextBodyId>) {
+    next_id.0 = 0;
+}
+
+fn reset_simulation_time(mut simulation_time: ResMut<SimulationTime>) {
+    *simulation_time = SimulationTime::default();
+}
@@ -0,0 +1,86 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+
+/// 开普勒轨道根数，角度均为弧度。
+/// 参考平面为模拟中的 xz 平面，参考方向为 x 轴，轨道法向为 y 轴
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OrbitalElements {
+    pub semi_major_axis: f32,
+    pub eccentricity: f32,
+    pub inclination: f32,
+    pub ascending_node: f32,
+    pub argument_of_periapsis: f32,
+    pub true_anomaly: f32,
+}
+
+impl OrbitalElements {
+    /// 由周期求半长轴，`mu` 为 G * (M + m)
+    pub fn semi_major_axis_from_period(period: f32, mu: f32) -> f32 {
+        (mu * (period / TAU).powi(2)).cbrt()
+    }
+
+    /// 转换为相对中心天体的位置与速度
+    pub fn to_state_vectors(self, mu: f32) -> Option<(Vec3, Vec3)> {
+        let semi_latus_rectum = self.semi_major_axis * (1.0 - self.eccentricity.powi(2));
+        if !(semi_latus_rectum > 0.0 && mu > 0.0) { return None; }
+        let (sin_anomaly, cos_anomaly) = self.true_anomaly.sin_cos();
+        let denominator = 1.0 + self.eccentricity * cos_anomaly;
+        // 双曲轨道上超出渐近线的真近点角
+        if denominator <= 0.0 { return None; }
+        let radius = semi_latus_rectum / denominator;
+        let position = Vec3::new(radius * cos_anomaly, radius * sin_anomaly, 0.0);
+        let velocity = Vec3::new(-sin_anomaly, self.eccentricity + cos_anomaly, 0.0) * (mu / semi_latus_rectum).sqrt();
+        let rotation = Quat::from_rotation_z(self.ascending_node)
+            * Quat::from_rotation_x(self.inclination)
+            * Quat::from_rotation_z(self.argument_of_periapsis);
+        Some((to_simulation_frame(rotation * position), to_simulation_frame(rotation * velocity)))
+    }
+}
+
+// 轨道根数的标准坐标系以 z 为法向，映射到以 y 为法向的右手系
+fn to_simulation_frame(Vec3 { x, y, z }: Vec3) -> Vec3 {
+    Vec3::new(x, z, -y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn circular_orbit_matches_vis_viva() {
+        let elements = OrbitalElements {
+            semi_major_axis: 100.0,
+            eccentricity: 0.0,
+            inclination: 0.0,
+            ascending_node: 0.0,
+            argument_of_periapsis: 0.0,
+            true_anomaly: 1.0,
+        };
+        let (position, velocity) = elements.to_state_vectors(400.0).unwrap();
+        assert!((position.length() - 100.0).abs() < 1e-3);
+        assert!((velocity.length() - 2.0).abs() < 1e-5);
+        assert!(position.y.abs() < 1e-5 && velocity.y.abs() < 1e-5);
+        assert!(position.cross(velocity).normalize().abs_diff_eq(Vec3::Y, 1e-5));
+    }
+    #[test]
+    fn eccentric_orbit_periapsis() {
+        let elements = OrbitalElements {
+            semi_major_axis: 100.0,
+            eccentricity: 0.5,
+            inclination: 0.3,
+            ascending_node: 1.2,
+            argument_of_periapsis: 2.0,
+            true_anomaly: 0.0,
+        };
+        let (position, velocity) = elements.to_state_vectors(400.0).unwrap();
+        assert!((position.length() - 50.0).abs() < 1e-3);
+        // 近心点速度 sqrt(mu * (1 + e) / (a * (1 - e)))
+        assert!((velocity.length() - 12.0_f32.sqrt()).abs() < 1e-4);
+        assert!(position.dot(velocity).abs() < 1e-2);
+    }
+    #[test]
+    fn period_to_semi_major_axis() {
+        let semi_major_axis = OrbitalElements::semi_major_axis_from_period(TAU * 500.0, 400.0);
+        assert!((semi_major_axis - 1.0e8_f32.cbrt()).abs() < 1e-2);
+    }
+}
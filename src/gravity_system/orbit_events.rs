@@ -0,0 +1,188 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use super::{
+    gravitation::GravitationComp,
+    motion::{MotionComp, SimulationTime},
+    particle::TestParticle,
+    running_state::ResetEvent,
+    scenario::Scenario,
+    ship::dominant_attractor,
+    GravityStatusUpdateSet,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApsisKind {
+    Periapsis,
+    Apoapsis,
+}
+
+/// 相对主导引力源经过近心点或远心点
+#[derive(Event, Debug, Clone)]
+pub struct ApsisEvent {
+    pub kind: ApsisKind,
+    pub body: Entity,
+    pub attractor: Entity,
+    pub time: f64,
+    pub distance: f32,
+    pub relative_speed: f32,
+}
+
+/// 两天体在近距离范围内达到最近点
+#[derive(Event, Debug, Clone)]
+pub struct CloseApproachEvent {
+    pub body: Entity,
+    pub other: Entity,
+    pub time: f64,
+    pub distance: f32,
+    pub relative_speed: f32,
+}
+
+/// 上一步相对主导引力源的径向速度
+#[derive(Component, Debug, Clone, Copy)]
+struct ApsisTracker {
+    attractor: Entity,
+    radial_velocity: f32,
+}
+
+/// 处于近距离范围内的天体对及其上一步的距离，已报告过最近点的记为 None
+#[derive(Resource, Debug, Default)]
+struct CloseApproaches(HashMap<(Entity, Entity), Option<f32>>);
+
+pub struct OrbitEventsPlugin;
+impl Plugin for OrbitEventsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CloseApproaches>();
+        app.add_event::<ApsisEvent>();
+        app.add_event::<CloseApproachEvent>();
+        app.add_systems(FixedUpdate, (detect_apsides, detect_close_approaches).in_set(GravityStatusUpdateSet::Analysis));
+        app.add_systems(PostUpdate, reset_close_approaches.run_if(on_event::<ResetEvent>()));
+    }
+}
+
+// 主导引力源取对该天体加速度贡献 m / r² 最大者，径向速度由负变正为近心点，由正变负为远心点
+fn detect_apsides(
+    mut commands: Commands,
+    mut bodies: Query<(Entity, &Transform, &MotionComp, Option<&mut ApsisTracker>), Without<TestParticle>>,
+    attractors: Query<(Entity, &Transform, &GravitationComp, Option<&MotionComp>), Without<TestParticle>>,
+    simulation_time: Res<SimulationTime>,
+    mut apsis_events: EventWriter<ApsisEvent>,
+) {
+    let attractors: Vec<_> = attractors.iter().collect();
+    for (entity, transform, motion, tracker) in bodies.iter_mut() {
+        let candidates = attractors.iter()
+            .map(|(other, position, gravitation, _)| (position.translation, if *other == entity { 0.0 } else { gravitation.mass }));
+        let Some(index) = dominant_attractor(transform.translation, candidates) else { continue; };
+        let (attractor, attractor_transform, _, attractor_motion) = attractors[index];
+        let offset = transform.translation - attractor_transform.translation;
+        let relative_velocity = motion.velocity - attractor_motion.map_or(Vec3::ZERO, |motion| motion.velocity);
+        let radial_velocity = offset.dot(relative_velocity) / offset.length();
+        let Some(mut tracker) = tracker else {
+            commands.entity(entity).insert(ApsisTracker { attractor, radial_velocity });
+            continue;
+        };
+        let current = ApsisTracker { attractor, radial_velocity };
+        if let Some(kind) = apsis_crossing(*tracker, current) {
+            apsis_events.send(ApsisEvent {
+                kind,
+                body: entity,
+                attractor,
+                time: simulation_time.elapsed,
+                distance: offset.length(),
+                relative_speed: relative_velocity.length(),
+            });
+        }
+        *tracker = current;
+    }
+}
+
+/// 主导引力源切换时径向速度不可比较，不报告
+fn apsis_crossing(previous: ApsisTracker, current: ApsisTracker) -> Option<ApsisKind> {
+    if previous.attractor != current.attractor { return None; }
+    if previous.radial_velocity < 0.0 && current.radial_velocity >= 0.0 {
+        Some(ApsisKind::Periapsis)
+    } else if previous.radial_velocity > 0.0 && current.radial_velocity <= 0.0 {
+        Some(ApsisKind::Apoapsis)
+    } else {
+        None
+    }
+}
+
+fn detect_close_approaches(
+    scenario: Res<Scenario>,
+    bodies: Query<(Entity, &Transform, &MotionComp), Without<TestParticle>>,
+    simulation_time: Res<SimulationTime>,
+    mut approaches: ResMut<CloseApproaches>,
+    mut approach_events: EventWriter<CloseApproachEvent>,
+) {
+    let Some(threshold) = scenario.close_approach_distance else { return; };
+    for [(entity, transform, motion), (other, other_transform, other_motion)] in bodies.iter_combinations() {
+        let key = if entity < other { (entity, other) } else { (other, entity) };
+        let distance = transform.translation.distance(other_transform.translation);
+        if let Some(closest) = track_approach(&mut approaches.0, key, distance, threshold) {
+            approach_events.send(CloseApproachEvent {
+                body: key.0,
+                other: key.1,
+                time: simulation_time.elapsed,
+                distance: closest,
+                relative_speed: (motion.velocity - other_motion.velocity).length(),
+            });
+        }
+    }
+}
+
+/// 距离开始增大时返回最近距离；每次进入范围只报告一次，离开范围后重新计
+fn track_approach(approaches: &mut HashMap<(Entity, Entity), Option<f32>>, key: (Entity, Entity), distance: f32, threshold: f32) -> Option<f32> {
+    if distance > threshold {
+        approaches.remove(&key);
+        return None;
+    }
+    let previous = approaches.entry(key).or_insert(Some(distance));
+    let previous_distance = (*previous)?;
+    if distance > previous_distance {
+        *previous = None;
+        Some(previous_distance)
+    } else {
+        *previous = Some(distance);
+        None
+    }
+}
+
+fn reset_close_approaches(mut approaches: ResMut<CloseApproaches>) {
+    approaches.0.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn radial_velocity_sign_change_marks_apsides() {
+        let attractor = Entity::from_raw(1);
+        let tracker = |radial_velocity| ApsisTracker { attractor, radial_velocity };
+        assert_eq!(apsis_crossing(tracker(-1.0), tracker(0.5)), Some(ApsisKind::Periapsis));
+        assert_eq!(apsis_crossing(tracker(1.0), tracker(-0.5)), Some(ApsisKind::Apoapsis));
+        assert_eq!(apsis_crossing(tracker(-1.0), tracker(-0.5)), None);
+        assert_eq!(apsis_crossing(tracker(1.0), tracker(0.5)), None);
+    }
+    #[test]
+    fn switching_attractor_reports_no_apsis() {
+        let (near, far) = (Entity::from_raw(1), Entity::from_raw(2));
+        let bodies = [(Vec3::ZERO, 1.0), (Vec3::X * 10.0, 100.0)];
+        // 靠近小天体时它占主导，越过引力平衡点后切换到大天体
+        assert_eq!(dominant_attractor(Vec3::X * 0.5, bodies), Some(0));
+        assert_eq!(dominant_attractor(Vec3::X * 2.0, bodies), Some(1));
+        let previous = ApsisTracker { attractor: near, radial_velocity: 1.0 };
+        let current = ApsisTracker { attractor: far, radial_velocity: -1.0 };
+        assert_eq!(apsis_crossing(previous, current), None);
+    }
+    #[test]
+    fn one_close_approach_per_pass_rearmed_after_leaving() {
+        let key = (Entity::from_raw(1), Entity::from_raw(2));
+        let mut approaches = HashMap::default();
+        let events: Vec<_> = [50.0, 30.0, 20.0, 25.0, 22.0, 18.0, 35.0, 45.0, 30.0, 10.0, 15.0]
+            .into_iter()
+            .filter_map(|distance| track_approach(&mut approaches, key, distance, 40.0))
+            .collect();
+        assert_eq!(events, vec![20.0, 10.0]);
+    }
+}
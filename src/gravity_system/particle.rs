@@ -0,0 +1,113 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use super::{
+    collision_detection::CollisionDetection,
+    motion::MotionComp,
+    scenario::RingJson,
+    GravityStatusUpdateSet,
+};
+
+/// 只受引力、不产生引力的测试粒子
+#[derive(Component)]
+pub struct TestParticle;
+
+/// 所有粒子共用同一个网格和材质，渲染时会被自动合批为实例化绘制
+#[derive(Resource)]
+pub struct ParticleAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+impl FromWorld for ParticleAssets {
+    fn from_world(world: &mut World) -> Self {
+        let mesh = world.resource_mut::<Assets<Mesh>>().add(Sphere::new(1.0).mesh().ico(1).unwrap());
+        let material = world.resource_mut::<Assets<StandardMaterial>>().add(StandardMaterial {
+            base_color: Color::srgb(0.85, 0.8, 0.7),
+            unlit: true,
+            ..default()
+        });
+        Self { mesh, material }
+    }
+}
+
+#[derive(Bundle)]
+pub struct Particle {
+    motion: MotionComp,
+    model: PbrBundle,
+    test_particle: TestParticle,
+}
+
+impl Particle {
+    pub fn new(position: Vec3, velocity: Vec3, radius: f32, assets: &ParticleAssets) -> Self {
+        Self {
+            motion: MotionComp {
+                velocity,
+                acceleration: Vec3::ZERO,
+                angular_velocity: Vec3::ZERO,
+            },
+            model: PbrBundle {
+                mesh: assets.mesh.clone(),
+                material: assets.material.clone(),
+                transform: Transform::from_translation(position).with_scale(Vec3::splat(radius)),
+                ..default()
+            },
+            test_particle: TestParticle,
+        }
+    }
+}
+
+pub struct ParticlePlugin;
+impl Plugin for ParticlePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ParticleAssets>();
+        app.add_systems(FixedUpdate, absorb_particles.in_set(GravityStatusUpdateSet::CollisionDetection));
+    }
+}
+
+/// `center` 为环中心的位置、速度与质量
+pub fn spawn_ring(
+    commands: &mut Commands,
+    assets: &ParticleAssets,
+    ring: &RingJson,
+    (center_position, center_velocity, center_mass): (Vec3, Vec3, f32),
+    gravitational_constant: f32,
+) {
+    let normal = Vec3::from(ring.normal).normalize_or(Vec3::Y);
+    let (tangent, bitangent) = normal.any_orthonormal_pair();
+    let mut rng = StdRng::seed_from_u64(ring.seed);
+    let inner_squared = ring.inner_radius.powi(2);
+    let outer_squared = ring.outer_radius.powi(2);
+    let particles: Vec<Particle> = (0..ring.count).map(|_| {
+        // 按面积均匀采样半径
+        let radius = rng.gen_range(inner_squared..=outer_squared).sqrt();
+        let angle = rng.gen_range(0.0..TAU);
+        let radial = tangent * angle.cos() + bitangent * angle.sin();
+        let height = normal * ring.thickness * rng.gen_range(-0.5..=0.5);
+        let speed = (gravitational_constant * center_mass / radius).sqrt();
+        Particle::new(
+            center_position + radial * radius + height,
+            center_velocity + normal.cross(radial) * speed,
+            ring.particle_radius,
+            assets,
+        )
+    }).collect();
+    commands.spawn_batch(particles);
+}
+
+// 落入天体碰撞半径内的粒子被吸收
+fn absorb_particles(
+    mut commands: Commands,
+    particles: Query<(Entity, &Transform), With<TestParticle>>,
+    bodies: Query<(&Transform, &CollisionDetection), Without<TestParticle>>,
+) {
+    for (entity, transform) in particles.iter() {
+        let absorbed = bodies.iter().any(|(body_transform, collision)| {
+            body_transform.translation.distance_squared(transform.translation) <= collision.radius.powi(2)
+        });
+        if absorbed {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
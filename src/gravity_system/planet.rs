@@ -1,13 +1,24 @@
-use std::fs;
+use std::f32::consts::PI;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::Arc;
 
+use bevy::input::common_conditions::input_just_pressed;
 use bevy::prelude::*;
-use serde::{Deserialize, Serialize};
-use crate::asset_loader::SceneAssets;
-use crate::gravity_system::gravitation::GravitationComp;
+use bevy::utils::HashMap;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use crate::loading_state::{AppState, LoadingAssets};
+use crate::asset_loader::{EmissiveJson, EmissiveOverride, FitToRadius, ModelCatalog, SceneAssets};
+use crate::gravity_system::gravitation::{GravitationComp, PostNewtonian};
 use crate::gravity_system::motion::MotionComp;
 
 use super::collision_detection::{CollisionDetection, CollisionDetectionEvent};
-use super::running_state::{ResetEvent, RunningState};
+use super::motion::{BodyId, SimulationTime};
+use super::lighting::StarLightSource;
+use super::particle::{spawn_ring, Particle, ParticleAssets, TestParticle};
+use super::scenario::{PlanetJson, Scenario};
+use super::tides::TidalTorque;
+use super::replay::replaying;
+use super::running_state::{ResetEvent, RunOutcome, RunningState};
 use super::GravityStatusUpdateSet;
 
 #[derive(Bundle)]
@@ -15,6 +26,7 @@ struct Planet {
     gravitation: GravitationComp,
     motion: MotionComp,
     model: SceneBundle,
+    fit_to_radius: FitToRadius,
     collision_detection: CollisionDetection,
 }
 
@@ -24,6 +36,7 @@ impl Default for Planet {
             gravitation: GravitationComp::new(0.0),
             motion: MotionComp::default(),
             model: SceneBundle::default(),
+            fit_to_radius: FitToRadius(10.0),
             collision_detection: CollisionDetection { radius: 10.0 }
         }
     }
@@ -42,10 +55,40 @@ impl Planet {
                 ..default()
             },
             gravitation: GravitationComp::new(mass),
+            fit_to_radius: FitToRadius(radius),
             collision_detection: CollisionDetection { radius },
-            ..default()
         }
     }
+
+    fn from_json(planet: &PlanetJson, asset_model: Handle<Scene>) -> Self {
+        let mut rotation = Quat::IDENTITY;
+        if let Some(spin) = &planet.spin {
+            rotation = Quat::from_rotation_x(spin.axial_tilt.to_radians());
+        }
+        let mut planet_bundle = Self::new(
+            planet.mass,
+            Transform::from_translation(planet.position.into()).with_rotation(rotation),
+            planet.velocity.into(),
+            asset_model,
+            planet.radius
+        );
+        planet_bundle.motion.angular_velocity = match &planet.spin {
+            Some(spin) => spin.angular_velocity(),
+            None => random_spin(planet),
+        };
+        planet_bundle
+    }
+}
+
+// 以名称和初始位置为种子，同一场景每次生成的自转都相同
+fn random_spin(planet: &PlanetJson) -> Vec3 {
+    let mut hasher = DefaultHasher::new();
+    planet.name.hash(&mut hasher);
+    for coordinate in Vec3::from(planet.position).to_array() {
+        coordinate.to_bits().hash(&mut hasher);
+    }
+    let mut rng = StdRng::seed_from_u64(hasher.finish());
+    Vec3::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0))
 }
 
 #[derive(Component)]
@@ -53,119 +96,281 @@ pub struct SmallPlanet;
 #[derive(Component)]
 pub struct FixedStar;
 
+// 只有质量至少为小天体 10 倍的天体才会将其潮汐瓦解
+const TIDAL_DISRUPTION_MASS_RATIO: f32 = 10.0;
+
+/// 洛希极限潮汐瓦解策略，按 T 键切换
+#[derive(Resource, Debug, Default)]
+pub struct TidalDisruption {
+    pub enabled: bool,
+    pub fragments: u32,
+}
+
+impl TidalDisruption {
+    /// 场景未开启时也给出默认碎片数，按 T 键开启后同样可用
+    pub fn from_scenario(scenario: &Scenario) -> Self {
+        Self {
+            enabled: scenario.tidal_disruption.is_some(),
+            fragments: scenario.tidal_disruption.clone().unwrap_or_default().fragments,
+        }
+    }
+}
+
+#[derive(Event, Debug)]
+pub struct TidalDisruptionEvent {
+    pub entity: Entity,
+    pub primary: Entity,
+    pub time: f64,
+}
+
+/// 场景生成的所有实体，重置时一并清除
+type ScenarioEntityFilter = Or<(With<SmallPlanet>, With<FixedStar>, With<TestParticle>)>;
+
 pub struct PlanetPlugin;
 impl Plugin for PlanetPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(PostStartup, spawn_planets);
-        app.add_systems(Update, self_rotate);
-        app.add_systems(Update,
+        let scenario = Scenario::from_args(&mut app.world_mut().get_resource_or_insert_with(LoadingAssets::default));
+        app.insert_resource(scenario);
+        app.init_resource::<TidalDisruption>();
+        app.add_event::<TidalDisruptionEvent>();
+        app.add_systems(OnEnter(AppState::Running), spawn_planets);
+        app.add_systems(PostUpdate,
             (clear_planets, spawn_planets).chain().run_if(on_event::<ResetEvent>()));
         app.add_systems(FixedUpdate,
             handle_planet_collision.chain()
-                        .after(GravityStatusUpdateSet::CollisionDetection)
+                        .after(GravityStatusUpdateSet::PositionUpdate)
+                        .before(GravityStatusUpdateSet::Analysis)
                         .run_if(on_event::<CollisionDetectionEvent>()));
+        app.add_systems(FixedUpdate,
+            handle_tidal_disruption
+                        .after(GravityStatusUpdateSet::PositionUpdate)
+                        .before(GravityStatusUpdateSet::Analysis)
+                        .run_if(|policy: Res<TidalDisruption>| policy.enabled));
+        app.add_systems(Update, toggle_tidal_disruption.run_if(input_just_pressed(KeyCode::KeyT).and_then(not(replaying))));
+        app.add_systems(Update, log_tidal_disruption.run_if(on_event::<TidalDisruptionEvent>()));
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct Vec3Json {
-    x: f32,
-    y: f32,
-    z: f32,
+fn spawn_planets(
+    mut commands: Commands,
+    asset_model: Res<SceneAssets>,
+    model_catalog: Res<ModelCatalog>,
+    particle_assets: Res<ParticleAssets>,
+    scenario: Res<Scenario>,
+) {
+    let barycentric;
+    let scenario = if scenario.barycentric {
+        barycentric = scenario.to_barycentric_frame();
+        &barycentric
+    } else {
+        scenario.as_ref()
+    };
+    let stars = scenario.fixed_stars.iter().map(|planet| (planet, true));
+    let planets = scenario.planets.iter().map(|planet| (planet, false));
+    for (index, (planet, star)) in stars.chain(planets).enumerate() {
+        let key = planet.name.clone().unwrap_or_else(|| format!("body {}", index));
+        spawn_planet(&mut commands, planet_source(planet.clone(), &key, star, &asset_model, &model_catalog));
+    }
+    commands.insert_resource(scenario.units);
+    commands.insert_resource(TidalDisruption::from_scenario(scenario));
+    commands.insert_resource(PostNewtonian {
+        enabled: scenario.post_newtonian.is_some(),
+        speed_of_light: scenario.post_newtonian.and_then(|config| config.speed_of_light)
+            .unwrap_or_else(|| scenario.units.speed_of_light()),
+    });
+    match &scenario.tides {
+        Some(tides) => commands.insert_resource(TidalTorque { strength: tides.strength }),
+        None => commands.remove_resource::<TidalTorque>(),
+    }
+    let gravitational_constant = scenario.units.gravitational_constant();
+    let total_mass = scenario.fixed_stars.iter().chain(scenario.planets.iter()).map(|planet| planet.mass).sum();
+    for ring in scenario.rings.iter() {
+        let center = match ring.center.as_deref().and_then(|name| scenario.find_body(name)) {
+            Some(planet) => (planet.position.into(), planet.velocity.into(), planet.mass),
+            None => (Vec3::ZERO, Vec3::ZERO, total_mass),
+        };
+        spawn_ring(&mut commands, &particle_assets, ring, center, gravitational_constant);
+    }
 }
-#[derive(Serialize, Deserialize ,Debug)]
-struct PlanetJson {
-    mass: f32,
-    position: Vec3Json,
-    velocity: Vec3Json,
-    radius: f32
+
+/// 生成天体所用的描述，回退时据此重新生成已被移除的天体
+#[derive(Debug, Clone)]
+pub struct PlanetSpawn {
+    pub planet: PlanetJson,
+    pub scene: Handle<Scene>,
+    pub emissive: Option<EmissiveJson>,
+    pub star: bool,
 }
-fn get_planets_from_json(path: &'static str) -> Option<Vec<PlanetJson>> {
-    let fixed_stars = fs::read_to_string(path).ok();
-    if let Some(fixed_stars) = fixed_stars {
-        serde_json::from_str::<Vec<PlanetJson>>(&fixed_stars).ok()
+
+#[derive(Component, Debug, Clone)]
+pub struct PlanetSource(pub Arc<PlanetSpawn>);
+
+/// 按模型目录挑选模型，`key` 在未指定模型时决定随机挑选的结果
+pub fn planet_source(planet: PlanetJson, key: &str, star: bool, scene_assets: &SceneAssets, model_catalog: &ModelCatalog) -> PlanetSource {
+    let model = model_catalog.pick(planet.model.as_deref(), key, star);
+    let fallback = if star { &scene_assets.asteroids } else { &scene_assets.planet };
+    let scene = model.map_or_else(|| fallback.clone(), |model| model.scene.clone());
+    let emissive = planet.emissive.or(model.and_then(|model| model.emissive));
+    PlanetSource(Arc::new(PlanetSpawn { planet, scene, emissive, star }))
+}
+
+pub fn spawn_planet(commands: &mut Commands, source: PlanetSource) -> Entity {
+    let spawn = source.0.clone();
+    let mut entity = commands.spawn(Planet::from_json(&spawn.planet, spawn.scene.clone()));
+    if spawn.star {
+        entity.insert((FixedStar, StarLightSource(spawn.planet.light.unwrap_or_default())));
     } else {
-        None
-    }
-}
-
-fn spawn_planets(mut commands: Commands, asset_model: Res<SceneAssets>) {
-    let fixed_stars = get_planets_from_json("assets/json/fixed_stars.json");
-    if let Some(fixed_stars) = fixed_stars {
-        for PlanetJson { mass, position, velocity, radius } in fixed_stars {
-            commands.spawn((Planet::new(
-                mass,
-                Transform {
-                    translation: Vec3::new(position.x, position.y, position.z),
-                    scale: Vec3::splat(radius / 2.0),
-                    ..default()
-                },
-                Vec3::new(velocity.x, velocity.y, velocity.z),
-                asset_model.asteroids.clone(),
-                radius
-            ), FixedStar));
-        }
+        entity.insert(SmallPlanet);
     }
-    let planets = get_planets_from_json("assets/json/planets.json");
-    if let Some(planets) = planets {
-        for PlanetJson { mass, position, velocity, radius } in planets {
-            commands.spawn((
-                Planet::new(
-                    mass,
-                    Transform {
-                        translation: Vec3::new(position.x, position.y, position.z),
-                        scale: Vec3::splat(radius / 2.0),
-                        ..default()
-                    },
-                    Vec3::new(velocity.x, velocity.y, velocity.z),
-                    asset_model.planet.clone(),
-                    radius
-                ),
-                SmallPlanet
-            ));
-        }
+    if let Some(name) = &spawn.planet.name {
+        entity.insert(Name::new(name.clone()));
     }
+    if let Some(emissive) = spawn.emissive {
+        entity.insert(EmissiveOverride(emissive.to_linear()));
+    }
+    entity.insert(source);
+    entity.id()
 }
 
 fn clear_planets(
     mut commands: Commands,
-    planets: Query<Entity, Or<(With<SmallPlanet>, With<FixedStar>)>>
+    planets: Query<Entity, ScenarioEntityFilter>
 ) {
     for entity in planets.iter() {
         commands.entity(entity).despawn_recursive();
     }
 }
 
+// 位置更新后执行，只把相撞的两个天体回退到各自的接触时刻，其余天体保持本步结束时的位置
 fn handle_planet_collision(
     mut collision_events: EventReader<CollisionDetectionEvent>,
-    query: Query<(Entity, &mut Transform), Or<(With<SmallPlanet>, With<FixedStar>)>>,
-    mut running_state: ResMut<NextState<RunningState>>
+    mut query: Query<(&mut Transform, &MotionComp)>,
+    time: Res<Time>,
+    mut running_state: ResMut<NextState<RunningState>>,
+    mut outcome: ResMut<RunOutcome>,
 ) {
+    let mut rewinds: HashMap<Entity, f32> = HashMap::new();
     for event in collision_events.read() {
-        let t1 = query.get(event.entity).unwrap().1;
-        let t2 = query.get(event.other_entity).unwrap().1;
-        println!("Collision detected!{:#?}{:#?}", t1, t2);
+        info!("Collision detected!{:?} {:?} time of impact: {}", event.entity, event.other_entity, event.time_of_impact);
+        let rewind_seconds = time.delta_seconds() - event.time_of_impact;
+        for entity in [event.entity, event.other_entity] {
+            let rewind = rewinds.entry(entity).or_insert(0.0);
+            *rewind = rewind.max(rewind_seconds);
+        }
     }
+    for (entity, rewind_seconds) in rewinds {
+        if let Ok((mut transform, motion)) = query.get_mut(entity) {
+            transform.translation -= motion.velocity * rewind_seconds;
+        }
+    }
+    outcome.reason = "Collision".to_string();
     running_state.set(RunningState::End);
 }
 
-fn self_rotate(
-    mut query: Query<(&mut Transform, &MotionComp), Or<(With<SmallPlanet>, With<FixedStar>)>>,
-    time: Res<Time>
+// 流体洛希极限 d = 2.44 * R_M * (ρ_M / ρ_m)^(1/3)
+fn roche_limit(primary_radius: f32, primary_density: f32, satellite_density: f32) -> f32 {
+    2.44 * primary_radius * (primary_density / satellite_density).cbrt()
+}
+
+fn density(mass: f32, radius: f32) -> f32 {
+    mass / (4.0 / 3.0 * PI * radius.powi(3))
+}
+
+type DisruptableQuery<'a> = (Entity, &'a BodyId, &'a Transform, &'a GravitationComp, &'a CollisionDetection, &'a MotionComp);
+
+// 碎片均匀分布在原天体体积内，速度按刚体自转叠加，因此延续原天体的轨道
+fn handle_tidal_disruption(
+    mut commands: Commands,
+    small_planets: Query<DisruptableQuery, With<SmallPlanet>>,
+    primaries: Query<(Entity, &Transform, &GravitationComp, &CollisionDetection)>,
+    policy: Res<TidalDisruption>,
+    particle_assets: Res<ParticleAssets>,
+    simulation_time: Res<SimulationTime>,
+    mut disruption_events: EventWriter<TidalDisruptionEvent>,
 ) {
-    for (mut transform, motion) in query.iter_mut() {
-        transform.rotate_y(motion.self_rotation.y * time.delta_seconds());
-        transform.rotate_x(motion.self_rotation.x * time.delta_seconds());
-        transform.rotate_z(motion.self_rotation.z * time.delta_seconds());
+    for (entity, id, transform, gravitation, collision, motion) in small_planets.iter() {
+        let satellite_density = density(gravitation.mass, collision.radius);
+        let primary = primaries.iter().find(|(other, other_transform, other_gravitation, other_collision)| {
+            *other != entity
+                && other_gravitation.mass >= gravitation.mass * TIDAL_DISRUPTION_MASS_RATIO
+                && transform.translation.distance(other_transform.translation)
+                    < roche_limit(other_collision.radius, density(other_gravitation.mass, other_collision.radius), satellite_density)
+        });
+        let Some((primary, ..)) = primary else { continue; };
+        disruption_events.send(TidalDisruptionEvent {
+            entity,
+            primary,
+            time: simulation_time.elapsed,
+        });
+        commands.entity(entity).despawn_recursive();
+        let mut rng = StdRng::seed_from_u64(simulation_time.tick ^ id.0);
+        let (fragment_radius, fragments) = tidal_fragments(
+            transform.translation, motion.velocity, motion.angular_velocity, collision.radius, policy.fragments, &mut rng,
+        );
+        let fragments: Vec<Particle> = fragments.into_iter()
+            .map(|(position, velocity)| Particle::new(position, velocity, fragment_radius, &particle_assets))
+            .collect();
+        commands.spawn_batch(fragments);
+    }
+}
+
+/// 返回碎片半径与各碎片的 (位置, 速度)。碎片与原天体密度相同、总体积相等，
+/// 偏移量减去均值后质心与原天体重合，质心速度等于原天体速度
+fn tidal_fragments(position: Vec3, velocity: Vec3, angular_velocity: Vec3, radius: f32, count: u32, rng: &mut StdRng) -> (f32, Vec<(Vec3, Vec3)>) {
+    let fragment_radius = radius / (count.max(1) as f32).cbrt();
+    let mut offsets: Vec<Vec3> = (0..count).map(|_| loop {
+        let offset = Vec3::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0));
+        if offset.length_squared() <= 1.0 { break offset * radius; }
+    }).collect();
+    let mean = offsets.iter().sum::<Vec3>() / count.max(1) as f32;
+    offsets.iter_mut().for_each(|offset| *offset -= mean);
+    let fragments = offsets.into_iter()
+        .map(|offset| (position + offset, velocity + angular_velocity.cross(offset)))
+        .collect();
+    (fragment_radius, fragments)
+}
+
+fn log_tidal_disruption(mut disruption_events: EventReader<TidalDisruptionEvent>) {
+    for event in disruption_events.read() {
+        info!("Tidal disruption! {:?} inside the Roche limit of {:?} at t = {}", event.entity, event.primary, event.time);
     }
 }
 
+fn toggle_tidal_disruption(mut policy: ResMut<TidalDisruption>) {
+    policy.enabled = !policy.enabled;
+    info!("Tidal disruption {}", if policy.enabled { "enabled" } else { "disabled" });
+}
+
 #[cfg(test)]
 mod tests {
-  use super::*;
-  #[test]
-  fn it_works() {
-    let planets = get_planets_from_json("assets/json/fixed_stars.json");
-    println!("planets: {:?}", planets)
-  }
-}
\ No newline at end of file
+    use super::*;
+    #[test]
+    fn roche_limit_scales_with_density_ratio() {
+        assert!((roche_limit(10.0, 1.0, 1.0) - 24.4).abs() < 1e-4);
+        assert!((roche_limit(10.0, 8.0, 1.0) - 48.8).abs() < 1e-4);
+    }
+    #[test]
+    fn toggling_without_policy_still_fragments() {
+        let mut policy = TidalDisruption::from_scenario(&Scenario::default());
+        assert!(!policy.enabled);
+        policy.enabled = true;
+        let (_, fragments) = tidal_fragments(Vec3::ZERO, Vec3::X, Vec3::ZERO, 1.0, policy.fragments, &mut StdRng::seed_from_u64(1));
+        assert!(!fragments.is_empty());
+    }
+    #[test]
+    fn fragments_conserve_mass_and_momentum() {
+        let (mass, radius) = (1000.0, 5.0);
+        let (position, velocity, spin) = (Vec3::new(100.0, 0.0, 0.0), Vec3::new(0.0, 3.0, 0.0), Vec3::new(0.0, 0.0, 0.4));
+        let (fragment_radius, fragments) = tidal_fragments(position, velocity, spin, radius, 64, &mut StdRng::seed_from_u64(7));
+        assert_eq!(fragments.len(), 64);
+        // 碎片与原天体密度相同
+        let fragment_mass = density(mass, radius) * 4.0 / 3.0 * PI * fragment_radius.powi(3);
+        assert!((fragment_mass * fragments.len() as f32 - mass).abs() < mass * 1e-4);
+        let centroid = fragments.iter().map(|(position, _)| *position).sum::<Vec3>() / fragments.len() as f32;
+        let centroid_velocity = fragments.iter().map(|(_, velocity)| *velocity).sum::<Vec3>() / fragments.len() as f32;
+        assert!(centroid.distance(position) < 1e-3);
+        assert!(centroid_velocity.distance(velocity) < 1e-4);
+        // 碎片仍绕原天体中心自转
+        assert!(fragments.iter().any(|(_, fragment_velocity)| fragment_velocity.distance(velocity) > 0.1));
+    }
+}
@@ -0,0 +1,334 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+
+use bevy::input::common_conditions::input_just_pressed;
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts, EguiPlugin};
+use egui_plot::{Line, Plot};
+
+use crate::loading_state::AppState;
+
+use super::{
+    gravitation::GravitationComp,
+    motion::{BodyId, MotionComp, SimulationTime},
+    particle::TestParticle,
+    rewind::{restore_snapshot, RewindEvent},
+    running_state::ResetEvent,
+    units::UnitSystem,
+    GravityStatusUpdateSet,
+};
+
+const EXPORT_DIR: &str = "output/plots";
+// 每条曲线最多保留的采样点，超出后丢弃最旧的一半
+const MAX_SAMPLES: usize = 20_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlotQuantity {
+    Distance(BodyId, BodyId),
+    Speed(BodyId),
+    /// 所有有质量天体的动能与两两引力势能之和
+    TotalEnergy,
+    /// 相对主导引力源的轨道偏心率
+    Eccentricity(BodyId),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum QuantityKind {
+    Distance,
+    Speed,
+    TotalEnergy,
+    Eccentricity,
+}
+
+impl QuantityKind {
+    const ALL: [QuantityKind; 4] = [Self::Distance, Self::Speed, Self::TotalEnergy, Self::Eccentricity];
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Distance => "distance",
+            Self::Speed => "speed",
+            Self::TotalEnergy => "total energy",
+            Self::Eccentricity => "eccentricity",
+        }
+    }
+}
+
+#[derive(Debug)]
+struct PlotSeries {
+    quantity: PlotQuantity,
+    label: String,
+    /// (模拟时间, 数值)
+    points: Vec<[f64; 2]>,
+}
+
+/// 正在绘制的曲线与采样间隔，按 P 键显示面板
+#[derive(Resource, Debug)]
+pub struct Plots {
+    /// 每隔多少个固定步采样一次
+    pub sample_interval: u64,
+    series: Vec<PlotSeries>,
+    visible: bool,
+    kind: QuantityKind,
+    first: Option<BodyId>,
+    second: Option<BodyId>,
+}
+
+impl Default for Plots {
+    fn default() -> Self {
+        Self {
+            sample_interval: 8,
+            series: Vec::new(),
+            visible: false,
+            kind: QuantityKind::Distance,
+            first: None,
+            second: None,
+        }
+    }
+}
+
+impl Plots {
+    fn truncate_after(&mut self, time: f64) {
+        for series in self.series.iter_mut() {
+            let kept = series.points.partition_point(|point| point[0] <= time);
+            series.points.truncate(kept);
+        }
+    }
+}
+
+type SampleQuery<'a> = (&'a BodyId, &'a Transform, &'a MotionComp, Option<&'a GravitationComp>, Has<TestParticle>);
+
+pub struct PlotPlugin;
+impl Plugin for PlotPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+        app.init_resource::<Plots>();
+        app.add_systems(FixedUpdate, sample_plots.in_set(GravityStatusUpdateSet::Record));
+        app.add_systems(PostUpdate, clear_plots.run_if(on_event::<ResetEvent>()));
+        app.add_systems(PostUpdate, truncate_plots.after(restore_snapshot).run_if(on_event::<RewindEvent>()));
+        app.add_systems(Update, toggle_plot_panel.run_if(input_just_pressed(KeyCode::KeyP)));
+        app.add_systems(Update, plot_panel
+            .run_if(in_state(AppState::Running).and_then(|plots: Res<Plots>| plots.visible)));
+    }
+}
+
+fn toggle_plot_panel(mut plots: ResMut<Plots>) {
+    plots.visible = !plots.visible;
+}
+
+fn sample_plots(
+    bodies: Query<SampleQuery>,
+    simulation_time: Res<SimulationTime>,
+    units: Res<UnitSystem>,
+    mut plots: ResMut<Plots>,
+) {
+    if plots.series.is_empty() || !simulation_time.tick.is_multiple_of(plots.sample_interval.max(1)) { return; }
+    let gravitational_constant = units.gravitational_constant();
+    let states: Vec<(BodyId, Vec3, Vec3, f32, bool)> = bodies.iter()
+        .map(|(id, transform, motion, gravitation, test_particle)| {
+            (*id, transform.translation, motion.velocity, gravitation.map_or(0.0, |gravitation| gravitation.mass), test_particle)
+        })
+        .collect();
+    let find = |id: BodyId| states.iter().find(|state| state.0 == id);
+    for series in plots.series.iter_mut() {
+        let value = match series.quantity {
+            PlotQuantity::Distance(a, b) => find(a).zip(find(b)).map(|(a, b)| a.1.distance(b.1) as f64),
+            PlotQuantity::Speed(id) => find(id).map(|body| body.2.length() as f64),
+            PlotQuantity::TotalEnergy => {
+                let massive: Vec<(Vec3, Vec3, f32)> = states.iter()
+                    .filter(|state| !state.4 && state.3 > 0.0)
+                    .map(|state| (state.1, state.2, state.3))
+                    .collect();
+                Some(total_energy(&massive, gravitational_constant))
+            }
+            PlotQuantity::Eccentricity(id) => find(id).and_then(|body| {
+                let attractor = states.iter()
+                    .filter(|other| other.0 != id && !other.4 && other.3 > 0.0)
+                    .max_by(|a, b| {
+                        (a.3 / a.1.distance_squared(body.1)).total_cmp(&(b.3 / b.1.distance_squared(body.1)))
+                    })?;
+                let mu = gravitational_constant * (attractor.3 + body.3);
+                Some(eccentricity(body.1 - attractor.1, body.2 - attractor.2, mu) as f64)
+            }),
+        };
+        // 天体被移除后曲线停止增长
+        let Some(value) = value else { continue; };
+        if series.points.len() >= MAX_SAMPLES {
+            series.points.drain(..MAX_SAMPLES / 2);
+        }
+        series.points.push([simulation_time.elapsed, value]);
+    }
+}
+
+/// 天体为 (位置, 速度, 质量)，在 f64 下累加以免动能与势能相消时丢失精度
+pub fn total_energy(bodies: &[(Vec3, Vec3, f32)], gravitational_constant: f32) -> f64 {
+    let kinetic: f64 = bodies.iter()
+        .map(|(_, velocity, mass)| 0.5 * *mass as f64 * velocity.as_dvec3().length_squared())
+        .sum();
+    let mut potential = 0.0;
+    for (index, (position, _, mass)) in bodies.iter().enumerate() {
+        for (other_position, _, other_mass) in bodies[index + 1..].iter() {
+            let distance = position.as_dvec3().distance(other_position.as_dvec3());
+            potential -= gravitational_constant as f64 * *mass as f64 * *other_mass as f64 / distance;
+        }
+    }
+    kinetic + potential
+}
+
+/// 偏心率矢量 e = ((v² - μ/r) r - (r·v) v) / μ 的大小
+pub fn eccentricity(offset: Vec3, velocity: Vec3, mu: f32) -> f32 {
+    let eccentricity = ((velocity.length_squared() - mu / offset.length()) * offset - offset.dot(velocity) * velocity) / mu;
+    eccentricity.length()
+}
+
+fn plot_panel(
+    mut contexts: EguiContexts,
+    mut plots: ResMut<Plots>,
+    bodies: Query<(&BodyId, Option<&Name>), With<MotionComp>>,
+    units: Res<UnitSystem>,
+) {
+    let mut names: Vec<(BodyId, String)> = bodies.iter()
+        .map(|(id, name)| (*id, name.map_or_else(|| format!("body {}", id.0), |name| name.to_string())))
+        .collect();
+    names.sort_by_key(|(id, _)| *id);
+    let name_of = |id: Option<BodyId>| {
+        id.and_then(|id| names.iter().find(|(other, _)| *other == id)).map_or("-", |(_, name)| name.as_str()).to_string()
+    };
+    let plots = plots.as_mut();
+    egui::Window::new("Plots").default_width(420.0).show(contexts.ctx_mut(), |ui| {
+        ui.horizontal(|ui| {
+            ui.label("sample every");
+            ui.add(egui::DragValue::new(&mut plots.sample_interval).range(1..=600));
+            ui.label("ticks");
+        });
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("quantity").selected_text(plots.kind.label()).show_ui(ui, |ui| {
+                for kind in QuantityKind::ALL {
+                    ui.selectable_value(&mut plots.kind, kind, kind.label());
+                }
+            });
+            if matches!(plots.kind, QuantityKind::Distance | QuantityKind::Speed | QuantityKind::Eccentricity) {
+                let selected_name = name_of(plots.first);
+                body_picker(ui, "first", &mut plots.first, &names, &selected_name);
+            }
+            if plots.kind == QuantityKind::Distance {
+                let selected_name = name_of(plots.second);
+                body_picker(ui, "second", &mut plots.second, &names, &selected_name);
+            }
+            let quantity = match plots.kind {
+                QuantityKind::Distance => plots.first.zip(plots.second)
+                    .filter(|(a, b)| a != b)
+                    .map(|(a, b)| PlotQuantity::Distance(a, b)),
+                QuantityKind::Speed => plots.first.map(PlotQuantity::Speed),
+                QuantityKind::TotalEnergy => Some(PlotQuantity::TotalEnergy),
+                QuantityKind::Eccentricity => plots.first.map(PlotQuantity::Eccentricity),
+            };
+            if ui.add_enabled(quantity.is_some(), egui::Button::new("Add")).clicked() {
+                let quantity = quantity.unwrap();
+                let label = match quantity {
+                    PlotQuantity::Distance(a, b) => format!("distance {} - {}", name_of(Some(a)), name_of(Some(b))),
+                    PlotQuantity::Speed(id) => format!("speed {}", name_of(Some(id))),
+                    PlotQuantity::TotalEnergy => "total energy".to_string(),
+                    PlotQuantity::Eccentricity(id) => format!("eccentricity {}", name_of(Some(id))),
+                };
+                plots.series.push(PlotSeries { quantity, label, points: Vec::new() });
+            }
+        });
+        let mut removed = None;
+        for (index, series) in plots.series.iter().enumerate() {
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label(format!("{} ({})", series.label, value_unit(series.quantity, &units)));
+                if ui.button("Export CSV").clicked() {
+                    export_series(series, &units);
+                }
+                if ui.button("Remove").clicked() {
+                    removed = Some(index);
+                }
+            });
+            Plot::new(("plot", index)).height(140.0).show(ui, |plot_ui| {
+                plot_ui.line(Line::new(series.points.clone()).name(&series.label));
+            });
+        }
+        if let Some(index) = removed {
+            plots.series.remove(index);
+        }
+    });
+}
+
+fn body_picker(ui: &mut egui::Ui, id: &str, selected: &mut Option<BodyId>, names: &[(BodyId, String)], selected_name: &str) {
+    egui::ComboBox::from_id_salt(id).selected_text(selected_name).show_ui(ui, |ui| {
+        for (body, name) in names.iter() {
+            ui.selectable_value(selected, Some(*body), name);
+        }
+    });
+}
+
+fn value_unit(quantity: PlotQuantity, units: &UnitSystem) -> String {
+    match quantity {
+        PlotQuantity::Distance(..) => units.length_unit().to_string(),
+        PlotQuantity::Speed(_) => units.velocity_unit().to_string(),
+        PlotQuantity::TotalEnergy => format!("{}·{}²/{}²", units.mass_unit(), units.length_unit(), units.time_unit()),
+        PlotQuantity::Eccentricity(_) => "1".to_string(),
+    }
+}
+
+fn export_series(series: &PlotSeries, units: &UnitSystem) {
+    let file_name: String = series.label.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+        .collect();
+    let path = format!("{}/{}.csv", EXPORT_DIR, file_name);
+    let result = fs::create_dir_all(EXPORT_DIR).and_then(|_| {
+        let mut writer = BufWriter::new(File::create(&path)?);
+        writeln!(writer, "time ({}),{} ({})", units.time_unit(), series.label, value_unit(series.quantity, units))?;
+        for [time, value] in series.points.iter() {
+            writeln!(writer, "{},{}", time, value)?;
+        }
+        writer.flush()
+    });
+    match result {
+        Ok(()) => info!("{} samples exported to {}", series.points.len(), path),
+        Err(err) => error!("failed to export plot to {}: {}", path, err),
+    }
+}
+
+// 重置后 BodyId 从 0 重新分配，旧曲线不再对应原天体
+fn clear_plots(mut plots: ResMut<Plots>) {
+    plots.series.clear();
+    plots.first = None;
+    plots.second = None;
+}
+
+// 回退后模拟时间已恢复为快照时刻，丢弃之后的采样点
+fn truncate_plots(mut plots: ResMut<Plots>, simulation_time: Res<SimulationTime>) {
+    plots.truncate_after(simulation_time.elapsed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn circular_orbit_has_zero_eccentricity_and_negative_energy() {
+        let (gravitational_constant, mass, radius) = (1.0_f32, 1000.0, 10.0);
+        let speed = (gravitational_constant * mass / radius).sqrt();
+        let eccentricity = eccentricity(Vec3::X * radius, Vec3::Z * speed, gravitational_constant * mass);
+        assert!(eccentricity < 1e-5, "e = {}", eccentricity);
+        let bodies = [(Vec3::ZERO, Vec3::ZERO, mass), (Vec3::X * radius, Vec3::Z * speed, 1.0)];
+        // 圆轨道上动能为势能绝对值的一半
+        let energy = total_energy(&bodies, gravitational_constant);
+        assert!((energy + 50.0).abs() < 1e-3, "E = {}", energy);
+    }
+    #[test]
+    fn rewind_drops_points_after_snapshot() {
+        let mut plots = Plots::default();
+        plots.series.push(PlotSeries {
+            quantity: PlotQuantity::TotalEnergy,
+            label: "total energy".to_string(),
+            points: (0..10).map(|step| [step as f64, 0.0]).collect(),
+        });
+        plots.truncate_after(4.0);
+        assert_eq!(plots.series[0].points.last(), Some(&[4.0, 0.0]));
+        assert_eq!(plots.series[0].points.len(), 5);
+    }
+}
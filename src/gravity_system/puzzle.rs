@@ -0,0 +1,336 @@
+use bevy::prelude::*;
+
+use crate::loading_state::AppState;
+
+use super::{
+    collision_detection::CollisionDetection,
+    motion::{MotionComp, SimulationTime},
+    particle::TestParticle,
+    replay::replaying,
+    running_state::{ResetEvent, RunOutcome, RunResult, RunningState},
+    scenario::{Scenario, StarThresholdJson, StarsJson},
+    units::UnitSystem,
+    GravityStatusUpdateSet,
+};
+
+// 探测器出生在距发射天体中心该倍数半径处
+const LAUNCH_ALTITUDE: f32 = 1.5;
+const PROBE_RADIUS: f32 = 0.4;
+// 方向键每秒转过的角度，以及每秒调整的 Δv 占预算的比例
+const HEADING_RATE: f32 = 90.0;
+const DELTA_V_RATE: f32 = 0.25;
+
+/// 关卡中由玩家发射的探测器，是只受引力的测试粒子
+#[derive(Component)]
+pub struct Probe;
+
+/// 沿与参考速度（发射前为发射天体的速度，之后为探测器的速度）夹角 `heading` 度的方向施加 Δv，
+/// 尚未发射时这一次点火即为发射
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ProbeBurnEvent {
+    pub heading: f32,
+    pub delta_v: f32,
+}
+
+#[derive(Resource, Debug, Default, Clone)]
+pub struct PuzzleState {
+    /// 下一次点火的方向与大小
+    pub heading: f32,
+    pub delta_v: f32,
+    pub used: f32,
+    /// 发射时的模拟时间，评星与时限都按发射后的飞行时间计
+    pub launched_at: Option<f64>,
+    finished: bool,
+}
+
+impl PuzzleState {
+    /// 供回退快照保存；判定结果不保存，回退后重新判定
+    pub fn unfinished(&self) -> Self {
+        Self { finished: false, ..self.clone() }
+    }
+}
+
+#[derive(Resource)]
+pub struct ProbeAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+impl FromWorld for ProbeAssets {
+    fn from_world(world: &mut World) -> Self {
+        let mesh = world.resource_mut::<Assets<Mesh>>().add(Sphere::new(1.0).mesh().ico(2).unwrap());
+        let material = world.resource_mut::<Assets<StandardMaterial>>().add(StandardMaterial {
+            base_color: Color::srgb(1.0, 0.6, 0.2),
+            emissive: LinearRgba::rgb(4.0, 2.0, 0.5),
+            ..default()
+        });
+        Self { mesh, material }
+    }
+}
+
+#[derive(Component)]
+struct PuzzleText;
+
+type BurnQuery<'a> = (Option<&'a Name>, &'a Transform, &'a mut MotionComp, Option<&'a CollisionDetection>, Has<Probe>);
+type NamedBodyQuery<'a> = (&'a Name, &'a Transform, Option<&'a MotionComp>, Has<Probe>);
+
+pub struct PuzzlePlugin;
+impl Plugin for PuzzlePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PuzzleState>();
+        app.init_resource::<ProbeAssets>();
+        app.add_event::<ProbeBurnEvent>();
+        app.add_systems(Startup, spawn_puzzle_text);
+        app.add_systems(Update, (aim_burn.run_if(not(replaying)), draw_aim)
+            .run_if(in_state(AppState::Running).and_then(puzzle_active)));
+        app.add_systems(Update, update_puzzle_text);
+        app.add_systems(PostUpdate, apply_probe_burn.run_if(on_event::<ProbeBurnEvent>()));
+        app.add_systems(PostUpdate, reset_puzzle.run_if(on_event::<ResetEvent>()));
+        app.add_systems(FixedUpdate, judge_puzzle.in_set(GravityStatusUpdateSet::Analysis).run_if(puzzle_active));
+    }
+}
+
+pub fn puzzle_active(scenario: Res<Scenario>) -> bool {
+    scenario.puzzle.is_some()
+}
+
+/// 抵达得一星，Δv 与飞行时间同时不超过对应上限时得两星或三星
+pub fn star_rating(delta_v: f32, time: f64, stars: &StarsJson) -> u8 {
+    let within = |threshold: &StarThresholdJson| delta_v <= threshold.delta_v && time <= threshold.time;
+    if within(&stars.three) {
+        3
+    } else if within(&stars.two) {
+        2
+    } else {
+        1
+    }
+}
+
+fn burn_direction(reference_velocity: Vec3, heading: f32) -> Vec3 {
+    Quat::from_rotation_y(heading.to_radians()) * reference_velocity.normalize_or(Vec3::X)
+}
+
+fn aim_burn(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    time: Res<Time>,
+    scenario: Res<Scenario>,
+    mut state: ResMut<PuzzleState>,
+    mut burn_events: EventWriter<ProbeBurnEvent>,
+) {
+    let Some(puzzle) = &scenario.puzzle else { return; };
+    let delta = time.delta_seconds();
+    if keyboard_input.pressed(KeyCode::ArrowLeft) {
+        state.heading = (state.heading + HEADING_RATE * delta).rem_euclid(360.0);
+    } else if keyboard_input.pressed(KeyCode::ArrowRight) {
+        state.heading = (state.heading - HEADING_RATE * delta).rem_euclid(360.0);
+    }
+    let remaining = (puzzle.delta_v_budget - state.used).max(0.0);
+    if keyboard_input.pressed(KeyCode::ArrowUp) {
+        state.delta_v += puzzle.delta_v_budget * DELTA_V_RATE * delta;
+    } else if keyboard_input.pressed(KeyCode::ArrowDown) {
+        state.delta_v -= puzzle.delta_v_budget * DELTA_V_RATE * delta;
+    }
+    state.delta_v = state.delta_v.clamp(0.0, remaining);
+    if keyboard_input.just_pressed(KeyCode::Enter) && state.delta_v > 0.0 {
+        burn_events.send(ProbeBurnEvent { heading: state.heading, delta_v: state.delta_v });
+    }
+}
+
+fn apply_probe_burn(
+    mut commands: Commands,
+    mut burn_events: EventReader<ProbeBurnEvent>,
+    mut bodies: Query<BurnQuery>,
+    scenario: Res<Scenario>,
+    simulation_time: Res<SimulationTime>,
+    assets: Res<ProbeAssets>,
+    mut state: ResMut<PuzzleState>,
+) {
+    let Some(puzzle) = &scenario.puzzle else { return; };
+    for event in burn_events.read() {
+        let delta_v = event.delta_v.min(puzzle.delta_v_budget - state.used);
+        if delta_v <= 0.0 || state.finished { continue; }
+        if let Some((.., mut motion, _, _)) = bodies.iter_mut().find(|(.., probe)| *probe) {
+            let direction = burn_direction(motion.velocity, event.heading);
+            motion.velocity += direction * delta_v;
+        } else if state.launched_at.is_none() {
+            let start = bodies.iter().find(|(name, .., probe)| !probe && name.is_some_and(|name| name.as_str() == puzzle.start));
+            let Some((_, transform, motion, Some(collision), _)) = start else {
+                error!("launch body {} not found", puzzle.start);
+                continue;
+            };
+            let direction = burn_direction(motion.velocity, event.heading);
+            spawn_probe(
+                &mut commands,
+                &assets,
+                transform.translation + direction * collision.radius * LAUNCH_ALTITUDE,
+                motion.velocity + direction * delta_v,
+            );
+            state.launched_at = Some(simulation_time.elapsed);
+        } else {
+            continue;
+        }
+        state.used += delta_v;
+        state.delta_v = state.delta_v.min(puzzle.delta_v_budget - state.used);
+    }
+}
+
+pub fn spawn_probe(commands: &mut Commands, assets: &ProbeAssets, position: Vec3, velocity: Vec3) -> Entity {
+    commands.spawn((
+        MotionComp {
+            velocity,
+            ..default()
+        },
+        PbrBundle {
+            mesh: assets.mesh.clone(),
+            material: assets.material.clone(),
+            transform: Transform::from_translation(position).with_scale(Vec3::splat(PROBE_RADIUS)),
+            ..default()
+        },
+        TestParticle,
+        Probe,
+        Name::new("Probe"),
+    )).id()
+}
+
+fn goal_center(scenario: &Scenario, bodies: &Query<NamedBodyQuery>) -> Option<Vec3> {
+    let goal = &scenario.puzzle.as_ref()?.goal;
+    match &goal.body {
+        Some(body) => bodies.iter()
+            .find(|(name, .., probe)| !probe && name.as_str() == body)
+            .map(|(_, transform, ..)| transform.translation),
+        None => goal.center.map(Vec3::from),
+    }
+}
+
+// 在碰撞处理之后执行，其他天体相撞结束模拟时同样判负
+fn judge_puzzle(
+    bodies: Query<NamedBodyQuery>,
+    scenario: Res<Scenario>,
+    simulation_time: Res<SimulationTime>,
+    units: Res<UnitSystem>,
+    mut state: ResMut<PuzzleState>,
+    mut outcome: ResMut<RunOutcome>,
+    mut next_state: ResMut<NextState<RunningState>>,
+) {
+    let Some(puzzle) = &scenario.puzzle else { return; };
+    if state.finished { return; }
+    let flight_time = state.launched_at.map(|launched_at| simulation_time.elapsed - launched_at);
+    let verdict = if matches!(*next_state, NextState::Pending(RunningState::End)) {
+        Some((RunResult::Lost, outcome.reason.clone()))
+    } else if let Some(flight_time) = flight_time {
+        match bodies.iter().find(|(.., probe)| *probe) {
+            None => Some((RunResult::Lost, "The probe crashed".to_string())),
+            Some((_, probe, ..)) if goal_center(&scenario, &bodies)
+                .is_some_and(|center| probe.translation.distance(center) <= puzzle.goal.radius) => {
+                let target = puzzle.goal.body.clone().unwrap_or_else(|| "the target region".to_string());
+                Some((RunResult::Won, format!("Reached {}", target)))
+            }
+            Some(_) if puzzle.time_limit.is_some_and(|limit| flight_time > limit) => Some((RunResult::Lost, "Out of time".to_string())),
+            Some(_) => None,
+        }
+    } else {
+        None
+    };
+    let Some((result, reason)) = verdict else { return; };
+    state.finished = true;
+    outcome.result = Some(result);
+    outcome.reason = reason;
+    outcome.details = vec![format!("Δv used: {:.2} / {:.2} {}", state.used, puzzle.delta_v_budget, units.velocity_unit())];
+    if let Some(flight_time) = flight_time {
+        outcome.details.push(format!("flight time: {:.1} {}", flight_time, units.time_unit()));
+        if result == RunResult::Won {
+            outcome.stars = Some(star_rating(state.used, flight_time, &puzzle.stars));
+        }
+    }
+    next_state.set(RunningState::End);
+}
+
+fn reset_puzzle(mut state: ResMut<PuzzleState>) {
+    *state = PuzzleState::default();
+}
+
+fn draw_aim(
+    mut gizmos: Gizmos,
+    bodies: Query<NamedBodyQuery>,
+    scenario: Res<Scenario>,
+    state: Res<PuzzleState>,
+) {
+    let Some(puzzle) = &scenario.puzzle else { return; };
+    let goal = goal_center(&scenario, &bodies);
+    if let Some(center) = goal {
+        gizmos.sphere(center, Quat::IDENTITY, puzzle.goal.radius, Color::srgb(0.4, 1.0, 0.5));
+    }
+    if state.finished { return; }
+    // 发射前以发射天体为参考，之后以探测器为参考
+    let reference = bodies.iter().find(|(name, .., probe)| match state.launched_at {
+        Some(_) => *probe,
+        None => !probe && name.as_str() == puzzle.start,
+    });
+    let Some((_, transform, motion, _)) = reference else { return; };
+    let (origin, velocity) = (transform.translation, motion.map_or(Vec3::ZERO, |motion| motion.velocity));
+    // 箭头长度为到目标距离的四分之一乘以点火量占预算的比例
+    let scale = goal.map_or(1.0, |center| origin.distance(center) * 0.25);
+    let length = scale * state.delta_v / puzzle.delta_v_budget.max(f32::EPSILON);
+    gizmos.arrow(origin, origin + burn_direction(velocity, state.heading) * length, Color::srgb(1.0, 0.6, 0.2));
+}
+
+fn spawn_puzzle_text(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 18.,
+                color: Color::WHITE,
+                ..default()
+            }
+        ).with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.),
+            right: Val::Px(10.),
+            ..default()
+        }),
+        PuzzleText,
+    ));
+}
+
+fn update_puzzle_text(
+    mut query: Query<&mut Text, With<PuzzleText>>,
+    scenario: Res<Scenario>,
+    state: Res<PuzzleState>,
+    simulation_time: Res<SimulationTime>,
+    units: Res<UnitSystem>,
+) {
+    let Ok(mut text) = query.get_single_mut() else { return; };
+    let Some(puzzle) = &scenario.puzzle else {
+        text.sections[0].value.clear();
+        return;
+    };
+    let velocity = units.velocity_unit();
+    let mut value = format!(
+        "Δv left: {:.2} / {:.2} {}\nnext burn: {:.2} {} at {:.0}°\n←/→ heading, ↑/↓ Δv, Enter {}",
+        puzzle.delta_v_budget - state.used, puzzle.delta_v_budget, velocity,
+        state.delta_v, velocity, state.heading,
+        if state.launched_at.is_some() { "burn" } else { "launch" },
+    );
+    if let Some(launched_at) = state.launched_at {
+        value += &format!("\nflight time: {:.1} {}", simulation_time.elapsed - launched_at, units.time_unit());
+        if let Some(limit) = puzzle.time_limit {
+            value += &format!(" / {:.1}", limit);
+        }
+    }
+    text.sections[0].value = value;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn stars_require_both_delta_v_and_time() {
+        let stars = StarsJson {
+            two: StarThresholdJson { delta_v: 20.0, time: 60.0 },
+            three: StarThresholdJson { delta_v: 12.0, time: 40.0 },
+        };
+        assert_eq!(star_rating(10.0, 30.0, &stars), 3);
+        assert_eq!(star_rating(10.0, 50.0, &stars), 2);
+        assert_eq!(star_rating(25.0, 30.0, &stars), 1);
+    }
+}
@@ -0,0 +1,385 @@
+use std::fs;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    gravitation::PostNewtonian,
+    motion::{BodyId, MotionComp},
+    maneuver::{ManeuverEditEvent, ManeuverNode},
+    planet::TidalDisruption,
+    puzzle::ProbeBurnEvent,
+    rewind::RewindEvent,
+    running_state::{ResetEvent, RunningState},
+    scenario::{arg_value, Scenario},
+    ship::ShipControls,
+    GravityStatusUpdateSet,
+};
+
+const DEFAULT_REPLAY_PATH: &str = "output/replay.json";
+const INTEGRATOR: &str = "semi_implicit_euler";
+
+/// 用户对模拟的一次修改，回放时在同一固定步之后重新施加
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplayCommand {
+    /// 按 R 长按或生成新星系，记录重置所用的完整场景
+    Reset { scenario: Box<Scenario> },
+    Rewind { tick: u64 },
+    SetTidalDisruption { enabled: bool },
+    SetPostNewtonian { enabled: bool },
+    /// 关卡中发射探测器或对其点火
+    ProbeBurn { heading: f32, delta_v: f32 },
+    /// 飞船油门与姿态输入发生变化
+    ShipControls { throttle: f32, rotation: [f32; 3] },
+    /// 机动节点面板中的修改，记录修改后的全部节点
+    SetManeuverNodes { nodes: Vec<ManeuverNode> },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RecordedCommand {
+    /// 施加修改前已经执行的固定步数，不随重置与回退归零
+    pub tick: u64,
+    pub command: ReplayCommand,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IntegratorJson {
+    pub name: String,
+    /// 固定步长，单位为秒
+    pub timestep: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReplayFile {
+    pub scenario: Scenario,
+    pub integrator: IntegratorJson,
+    pub commands: Vec<RecordedCommand>,
+    /// 第 i 项为第 i + 1 个固定步结束时的状态校验和
+    pub checksums: Vec<u64>,
+}
+
+impl ReplayFile {
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let replay = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        serde_json::from_str(&replay).map_err(|err| err.to_string())
+    }
+
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        if let Some(dir) = std::path::Path::new(path).parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string(self)?)
+    }
+}
+
+/// 已经执行的固定步数
+#[derive(Resource, Debug, Default)]
+pub struct ReplayClock {
+    pub tick: u64,
+}
+
+/// 本次固定更新是否推进模拟，在每步开始时确定
+#[derive(Resource, Debug)]
+pub struct TickGate(bool);
+
+#[derive(Resource, Debug)]
+struct ReplayRecorder {
+    path: String,
+    replay: ReplayFile,
+    /// 上一帧的潮汐瓦解与后牛顿修正开关，变化时记录
+    tidal_disruption: Option<bool>,
+    post_newtonian: Option<bool>,
+    ship_controls: ShipControls,
+}
+
+#[derive(Resource, Debug)]
+pub struct ReplayPlayback {
+    replay: ReplayFile,
+    next_command: usize,
+    verify: bool,
+    divergence: Option<u64>,
+}
+
+pub struct ReplayPlugin;
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReplayClock>();
+        app.insert_resource(TickGate(true));
+        let args: Vec<String> = std::env::args().collect();
+        // 须在 PlanetPlugin 之后添加，回放时覆盖其读取的场景
+        match arg_value(&args, "--replay") {
+            Some(path) => match ReplayFile::from_file(path) {
+                Ok(replay) => {
+                    app.insert_resource(replay.scenario.clone());
+                    app.insert_resource(ReplayPlayback {
+                        replay,
+                        next_command: 0,
+                        verify: args.iter().any(|arg| arg == "--verify"),
+                        divergence: None,
+                    });
+                }
+                Err(err) => error!("failed to load replay {}: {}", path, err),
+            },
+            None => {
+                let scenario = app.world().resource::<Scenario>().clone();
+                app.insert_resource(ReplayRecorder {
+                    path: arg_value(&args, "--record").unwrap_or(DEFAULT_REPLAY_PATH).to_string(),
+                    replay: ReplayFile {
+                        scenario,
+                        integrator: IntegratorJson { name: INTEGRATOR.to_string(), timestep: 0.0 },
+                        commands: Vec::new(),
+                        checksums: Vec::new(),
+                    },
+                    tidal_disruption: None,
+                    post_newtonian: None,
+                    ship_controls: ShipControls::default(),
+                });
+            }
+        }
+        app.add_systems(Startup, (
+            record_integrator.run_if(resource_exists::<ReplayRecorder>),
+            apply_integrator.run_if(resource_exists::<ReplayPlayback>),
+        ));
+        app.add_systems(FixedUpdate, update_tick_gate.before(GravityStatusUpdateSet::AccelerationUpdate));
+        app.add_systems(FixedUpdate, checksum_tick.in_set(GravityStatusUpdateSet::Record));
+        app.add_systems(Update, drive_playback.run_if(resource_exists::<ReplayPlayback>));
+        app.add_systems(PostUpdate, (record_commands, record_setting_changes).run_if(resource_exists::<ReplayRecorder>));
+        app.add_systems(Last, finish_replay.run_if(on_event::<AppExit>()));
+    }
+}
+
+/// 回放期间忽略键盘操纵，由录制的命令驱动
+pub fn replaying(playback: Option<Res<ReplayPlayback>>) -> bool {
+    playback.is_some()
+}
+
+/// 作为 GravityStatusUpdateSet 的运行条件
+pub fn tick_allowed(gate: Res<TickGate>) -> bool {
+    gate.0
+}
+
+fn record_integrator(time: Res<Time<Fixed>>, mut recorder: ResMut<ReplayRecorder>) {
+    recorder.replay.integrator.timestep = time.timestep().as_secs_f64();
+}
+
+fn apply_integrator(mut time: ResMut<Time<Fixed>>, playback: Res<ReplayPlayback>) {
+    let integrator = &playback.replay.integrator;
+    if integrator.name != INTEGRATOR {
+        warn!("replay was recorded with integrator {}, replaying with {}", integrator.name, INTEGRATOR);
+    }
+    time.set_timestep_seconds(integrator.timestep);
+}
+
+// 碰撞已请求结束时，同一帧内剩余的固定步不再推进；回放时停在下一条修改所在的步，
+// 等 Update 施加修改后再继续，因此修改总是落在与录制时相同的两步之间
+fn update_tick_gate(
+    mut gate: ResMut<TickGate>,
+    clock: Res<ReplayClock>,
+    next_state: Option<Res<NextState<RunningState>>>,
+    playback: Option<Res<ReplayPlayback>>,
+) {
+    // 时间线的暂停与结束一样在固定步内发出，须在状态切换前停下
+    let ending = next_state.is_some_and(|next_state| {
+        matches!(*next_state, NextState::Pending(RunningState::End | RunningState::Paused))
+    });
+    let waiting = playback.is_some_and(|playback| {
+        playback.replay.commands.get(playback.next_command).is_some_and(|recorded| recorded.tick <= clock.tick)
+    });
+    gate.0 = !ending && !waiting;
+}
+
+fn checksum_tick(
+    bodies: Query<(&BodyId, &Transform, Option<&MotionComp>)>,
+    mut clock: ResMut<ReplayClock>,
+    recorder: Option<ResMut<ReplayRecorder>>,
+    playback: Option<ResMut<ReplayPlayback>>,
+) {
+    let states: Vec<(BodyId, [f32; 13])> = bodies.iter()
+        .map(|(id, transform, motion)| {
+            let velocity = motion.map_or(Vec3::ZERO, |motion| motion.velocity);
+            let angular_velocity = motion.map_or(Vec3::ZERO, |motion| motion.angular_velocity);
+            let mut state = [0.0; 13];
+            state[0..3].copy_from_slice(&transform.translation.to_array());
+            state[3..7].copy_from_slice(&transform.rotation.to_array());
+            state[7..10].copy_from_slice(&velocity.to_array());
+            state[10..13].copy_from_slice(&angular_velocity.to_array());
+            (*id, state)
+        })
+        .collect();
+    let checksum = state_checksum(states);
+    let tick = clock.tick;
+    clock.tick += 1;
+    if let Some(mut recorder) = recorder {
+        recorder.replay.checksums.push(checksum);
+    }
+    let Some(mut playback) = playback else { return; };
+    if !playback.verify || playback.divergence.is_some() { return; }
+    let Some(&expected) = playback.replay.checksums.get(tick as usize) else { return; };
+    if checksum != expected {
+        playback.divergence = Some(tick + 1);
+        error!("replay diverged at tick {}: checksum {:016x}, recorded {:016x}", tick + 1, checksum, expected);
+    }
+}
+
+/// 按 BodyId 排序后对各分量的位模式做 FNV-1a，与实体顺序无关且不依赖标准库哈希的实现
+pub fn state_checksum(mut states: Vec<(BodyId, [f32; 13])>) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    states.sort_by_key(|(id, _)| *id);
+    let mut hash = OFFSET_BASIS;
+    let mut write = |bytes: &[u8]| {
+        for byte in bytes {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(PRIME);
+        }
+    };
+    for (id, state) in states.iter() {
+        write(&id.0.to_le_bytes());
+        for value in state {
+            write(&value.to_bits().to_le_bytes());
+        }
+    }
+    hash
+}
+
+// 在 PostUpdate 中与重置、回退的处理系统同一帧执行，此时本帧的固定步都已完成
+fn record_commands(
+    mut reset_events: EventReader<ResetEvent>,
+    mut rewind_events: EventReader<RewindEvent>,
+    mut burn_events: EventReader<ProbeBurnEvent>,
+    scenario: Res<Scenario>,
+    clock: Res<ReplayClock>,
+    mut recorder: ResMut<ReplayRecorder>,
+) {
+    let mut record = |command| recorder.replay.commands.push(RecordedCommand { tick: clock.tick, command });
+    for _ in reset_events.read() {
+        record(ReplayCommand::Reset { scenario: Box::new(scenario.clone()) });
+    }
+    for event in rewind_events.read() {
+        record(ReplayCommand::Rewind { tick: event.tick });
+    }
+    for event in burn_events.read() {
+        record(ReplayCommand::ProbeBurn { heading: event.heading, delta_v: event.delta_v });
+    }
+}
+
+// 开关与操纵没有对应的事件，与上一帧的值比较
+fn record_setting_changes(
+    mut edit_events: EventReader<ManeuverEditEvent>,
+    tidal_disruption: Res<TidalDisruption>,
+    post_newtonian: Res<PostNewtonian>,
+    ship_controls: Res<ShipControls>,
+    clock: Res<ReplayClock>,
+    mut recorder: ResMut<ReplayRecorder>,
+) {
+    let last_tidal_disruption = recorder.tidal_disruption.replace(tidal_disruption.enabled);
+    if last_tidal_disruption.is_some_and(|enabled| enabled != tidal_disruption.enabled) {
+        recorder.replay.commands.push(RecordedCommand {
+            tick: clock.tick,
+            command: ReplayCommand::SetTidalDisruption { enabled: tidal_disruption.enabled },
+        });
+    }
+    let last_post_newtonian = recorder.post_newtonian.replace(post_newtonian.enabled);
+    if last_post_newtonian.is_some_and(|enabled| enabled != post_newtonian.enabled) {
+        recorder.replay.commands.push(RecordedCommand {
+            tick: clock.tick,
+            command: ReplayCommand::SetPostNewtonian { enabled: post_newtonian.enabled },
+        });
+    }
+    if recorder.ship_controls != *ship_controls {
+        recorder.ship_controls = *ship_controls;
+        recorder.replay.commands.push(RecordedCommand {
+            tick: clock.tick,
+            command: ReplayCommand::ShipControls {
+                throttle: ship_controls.throttle,
+                rotation: ship_controls.rotation.to_array(),
+            },
+        });
+    }
+    for event in edit_events.read() {
+        recorder.replay.commands.push(RecordedCommand {
+            tick: clock.tick,
+            command: ReplayCommand::SetManeuverNodes { nodes: event.nodes.clone() },
+        });
+    }
+}
+
+fn drive_playback(
+    mut playback: ResMut<ReplayPlayback>,
+    clock: Res<ReplayClock>,
+    mut commands: Commands,
+    mut reset_event_writer: EventWriter<ResetEvent>,
+    mut rewind_event_writer: EventWriter<RewindEvent>,
+    mut burn_event_writer: EventWriter<ProbeBurnEvent>,
+    mut next_state: ResMut<NextState<RunningState>>,
+) {
+    while let Some(recorded) = playback.replay.commands.get(playback.next_command) {
+        if recorded.tick > clock.tick { break; }
+        match &recorded.command {
+            ReplayCommand::Reset { scenario: recorded_scenario } => {
+                commands.insert_resource(recorded_scenario.as_ref().clone());
+                reset_event_writer.send(ResetEvent);
+            }
+            ReplayCommand::Rewind { tick } => {
+                rewind_event_writer.send(RewindEvent { tick: *tick });
+            }
+            ReplayCommand::SetTidalDisruption { enabled } => {
+                let enabled = *enabled;
+                commands.add(move |world: &mut World| world.resource_mut::<TidalDisruption>().enabled = enabled);
+            }
+            ReplayCommand::SetPostNewtonian { enabled } => {
+                let enabled = *enabled;
+                commands.add(move |world: &mut World| world.resource_mut::<PostNewtonian>().enabled = enabled);
+            }
+            ReplayCommand::ProbeBurn { heading, delta_v } => {
+                burn_event_writer.send(ProbeBurnEvent { heading: *heading, delta_v: *delta_v });
+            }
+            ReplayCommand::ShipControls { throttle, rotation } => {
+                commands.insert_resource(ShipControls { throttle: *throttle, rotation: Vec3::from_array(*rotation) });
+            }
+            ReplayCommand::SetManeuverNodes { nodes } => {
+                let nodes = nodes.clone();
+                commands.add(move |world: &mut World| {
+                    world.send_event(ManeuverEditEvent { nodes });
+                });
+            }
+        }
+        next_state.set(RunningState::Running);
+        playback.next_command += 1;
+    }
+}
+
+fn finish_replay(
+    clock: Res<ReplayClock>,
+    recorder: Option<Res<ReplayRecorder>>,
+    playback: Option<Res<ReplayPlayback>>,
+) {
+    if let Some(recorder) = recorder {
+        match recorder.replay.save(&recorder.path) {
+            Ok(()) => info!("saved replay of {} ticks to {}", recorder.replay.checksums.len(), recorder.path),
+            Err(err) => error!("failed to save replay to {}: {}", recorder.path, err),
+        }
+    }
+    let Some(playback) = playback else { return; };
+    if !playback.verify { return; }
+    let recorded = playback.replay.checksums.len() as u64;
+    match playback.divergence {
+        Some(tick) => error!("replay verification failed: first divergence at tick {}", tick),
+        None => info!("replay verification passed for {} of {} recorded ticks", clock.tick.min(recorded), recorded),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn checksum_ignores_order_but_not_bits() {
+        let a = (BodyId(0), [1.0; 13]);
+        let b = (BodyId(1), [2.0; 13]);
+        assert_eq!(state_checksum(vec![a, b]), state_checksum(vec![b, a]));
+        let mut nudged = b;
+        nudged.1[4] = f32::from_bits(nudged.1[4].to_bits() + 1);
+        assert_ne!(state_checksum(vec![a, b]), state_checksum(vec![a, nudged]));
+    }
+}
@@ -0,0 +1,516 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use crate::asset_loader::SceneAssets;
+
+use super::{
+    collision_detection::CollisionDetection,
+    escape::Escaped,
+    gravitation::GravitationComp,
+    maneuver::ManeuverPlan,
+    motion::{BodyId, MotionComp, SimulationTime},
+    particle::{Particle, ParticleAssets, TestParticle},
+    planet::{spawn_planet, PlanetSource},
+    puzzle::{spawn_probe, Probe, ProbeAssets, PuzzleState},
+    running_state::{ResetEvent, RunningState},
+    scenario::Scenario,
+    ship::{spawn_player_ship, PlayerShip, ShipControls},
+    timeline::TimelineState,
+    GravityStatusUpdateSet,
+};
+
+// 每隔多少个固定步保存一次快照，以及最多保留的快照数
+const SNAPSHOT_INTERVAL_TICKS: u64 = 32;
+const SNAPSHOT_CAPACITY: usize = 200;
+const END_SCREEN_REWIND: f64 = 5.0;
+
+/// 重新生成天体时使用的方式
+#[derive(Debug, Clone)]
+enum BodyKind {
+    Planet(PlanetSource),
+    Particle,
+    /// 关卡中发射的探测器
+    Probe,
+    /// 玩家飞船及其剩余燃料
+    Ship(PlayerShip),
+}
+
+#[derive(Debug, Clone)]
+struct BodySnapshot {
+    id: BodyId,
+    kind: BodyKind,
+    translation: Vec3,
+    rotation: Quat,
+    velocity: Vec3,
+    angular_velocity: Vec3,
+    mass: Option<f32>,
+    radius: f32,
+    /// 逃逸后被冻结
+    frozen: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub tick: u64,
+    pub time: f64,
+    bodies: Vec<BodySnapshot>,
+    /// 关卡已用的 Δv 与发射时刻
+    puzzle: PuzzleState,
+    ship_controls: ShipControls,
+    /// 未执行的机动节点与正在进行的点火
+    maneuver_plan: ManeuverPlan,
+    /// 时间线中已触发的项与出现过的天体
+    timeline: TimelineState,
+}
+
+/// 快照环形缓冲区，满后丢弃最旧的快照
+#[derive(Resource, Debug, Default)]
+pub struct SnapshotHistory {
+    pub snapshots: VecDeque<Snapshot>,
+    /// 快照列表变化时递增，供时间轴界面刷新
+    version: u64,
+}
+
+impl SnapshotHistory {
+    fn push(&mut self, snapshot: Snapshot) {
+        // 从较早的快照继续运行后，丢弃原先那条时间线上更晚的快照
+        while self.snapshots.back().is_some_and(|last| last.tick >= snapshot.tick) {
+            self.snapshots.pop_back();
+        }
+        if self.snapshots.len() >= SNAPSHOT_CAPACITY {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(snapshot);
+        self.version += 1;
+    }
+}
+
+/// 回退到指定固定步的快照
+#[derive(Event, Debug, Clone, Copy)]
+pub struct RewindEvent {
+    pub tick: u64,
+}
+
+#[derive(Component)]
+struct Timeline;
+
+#[derive(Component)]
+struct TimelineSegment(u64);
+
+#[derive(Component)]
+struct RewindButton;
+
+pub struct RewindPlugin;
+impl Plugin for RewindPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SnapshotHistory>();
+        app.add_event::<RewindEvent>();
+        app.add_systems(FixedUpdate, take_snapshot.in_set(GravityStatusUpdateSet::Record));
+        app.add_systems(PostUpdate, restore_snapshot.run_if(on_event::<RewindEvent>()));
+        app.add_systems(PostUpdate, clear_snapshots.run_if(on_event::<ResetEvent>()));
+        app.add_systems(Startup, spawn_timeline);
+        app.add_systems(Update, update_timeline);
+        app.add_systems(Update, (handle_timeline_click, handle_rewind_button));
+        app.add_systems(OnEnter(RunningState::End), spawn_rewind_button);
+    }
+}
+
+type SnapshotQuery<'a> = (
+    &'a BodyId,
+    &'a Transform,
+    Option<&'a MotionComp>,
+    Option<&'a CollisionDetection>,
+    Option<&'a GravitationComp>,
+    &'a PlanetSource,
+);
+
+type ShipSnapshotQuery<'a> = (&'a BodyId, &'a Transform, Option<&'a MotionComp>, Option<&'a GravitationComp>, &'a PlayerShip);
+
+type RestoreQuery<'a> = (Entity, Option<&'a BodyId>, &'a mut Transform, Option<&'a mut MotionComp>);
+type RestoreFilter = Or<(With<BodyId>, With<MotionComp>)>;
+
+fn take_snapshot(
+    bodies: Query<SnapshotQuery>,
+    particles: Query<(&BodyId, &Transform, &MotionComp, Has<Probe>), With<TestParticle>>,
+    ships: Query<ShipSnapshotQuery>,
+    simulation_time: Res<SimulationTime>,
+    scenario: Res<Scenario>,
+    (puzzle, ship_controls, maneuver_plan, timeline): (Res<PuzzleState>, Res<ShipControls>, Res<ManeuverPlan>, Res<TimelineState>),
+    mut history: ResMut<SnapshotHistory>,
+) {
+    if !simulation_time.tick.is_multiple_of(SNAPSHOT_INTERVAL_TICKS) { return; }
+    let mut snapshot_bodies: Vec<BodySnapshot> = bodies.iter()
+        .map(|(id, transform, motion, collision, gravitation, source)| BodySnapshot {
+            id: *id,
+            kind: BodyKind::Planet(source.clone()),
+            translation: transform.translation,
+            rotation: transform.rotation,
+            velocity: motion.map_or(Vec3::ZERO, |motion| motion.velocity),
+            angular_velocity: motion.map_or(Vec3::ZERO, |motion| motion.angular_velocity),
+            mass: gravitation.map(|gravitation| gravitation.mass),
+            radius: collision.map_or(source.0.planet.radius, |collision| collision.radius),
+            frozen: motion.is_none(),
+        })
+        .collect();
+    snapshot_bodies.extend(particles.iter().map(|(id, transform, motion, probe)| BodySnapshot {
+        id: *id,
+        kind: if probe { BodyKind::Probe } else { BodyKind::Particle },
+        translation: transform.translation,
+        rotation: transform.rotation,
+        velocity: motion.velocity,
+        angular_velocity: motion.angular_velocity,
+        mass: None,
+        // 粒子的缩放即其半径
+        radius: transform.scale.x,
+        frozen: false,
+    }));
+    snapshot_bodies.extend(ships.iter().map(|(id, transform, motion, gravitation, ship)| BodySnapshot {
+        id: *id,
+        kind: BodyKind::Ship(*ship),
+        translation: transform.translation,
+        rotation: transform.rotation,
+        velocity: motion.map_or(Vec3::ZERO, |motion| motion.velocity),
+        angular_velocity: motion.map_or(Vec3::ZERO, |motion| motion.angular_velocity),
+        mass: gravitation.map(|gravitation| gravitation.mass),
+        radius: scenario.ship.as_ref().map_or(1.0, |ship| ship.radius),
+        frozen: motion.is_none(),
+    }));
+    history.push(Snapshot {
+        tick: simulation_time.tick,
+        time: simulation_time.elapsed,
+        bodies: snapshot_bodies,
+        puzzle: puzzle.unfinished(),
+        ship_controls: *ship_controls,
+        maneuver_plan: maneuver_plan.clone(),
+        timeline: timeline.clone(),
+    });
+}
+
+// 快照中已不存在的天体被移除，快照之后才被移除的天体（碰撞、瓦解、逃逸）重新生成
+pub fn restore_snapshot(
+    mut commands: Commands,
+    mut rewind_events: EventReader<RewindEvent>,
+    history: Res<SnapshotHistory>,
+    mut bodies: Query<RestoreQuery, RestoreFilter>,
+    (particle_assets, probe_assets, scene_assets): (Res<ParticleAssets>, Res<ProbeAssets>, Res<SceneAssets>),
+    mut simulation_time: ResMut<SimulationTime>,
+    session: (ResMut<PuzzleState>, ResMut<ShipControls>, ResMut<ManeuverPlan>, ResMut<TimelineState>),
+) {
+    let Some(event) = rewind_events.read().last() else { return; };
+    let Some(snapshot) = history.snapshots.iter().find(|snapshot| snapshot.tick == event.tick) else { return; };
+    let mut remaining: HashMap<BodyId, &BodySnapshot> = snapshot.bodies.iter().map(|body| (body.id, body)).collect();
+    for (entity, id, mut transform, motion) in bodies.iter_mut() {
+        // 还没分配编号的天体是在最近一个固定步中才生成的
+        let Some(body) = id.and_then(|id| remaining.remove(id)) else {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        };
+        transform.translation = body.translation;
+        transform.rotation = body.rotation;
+        if body.frozen {
+            commands.entity(entity).insert(Escaped).remove::<(MotionComp, GravitationComp, CollisionDetection)>();
+            continue;
+        }
+        let restored_motion = MotionComp {
+            velocity: body.velocity,
+            acceleration: Vec3::ZERO,
+            angular_velocity: body.angular_velocity,
+        };
+        match motion {
+            Some(mut motion) => *motion = restored_motion,
+            None => { commands.entity(entity).insert(restored_motion); }
+        }
+        // 逃逸后被冻结的天体恢复参与模拟
+        let mut entity = commands.entity(entity);
+        entity.remove::<Escaped>();
+        match &body.kind {
+            BodyKind::Planet(_) => { entity.insert(CollisionDetection { radius: body.radius }); }
+            BodyKind::Ship(ship) => { entity.insert((CollisionDetection { radius: body.radius }, *ship)); }
+            BodyKind::Particle | BodyKind::Probe => (),
+        }
+        if let Some(mass) = body.mass {
+            entity.insert(GravitationComp::new(mass));
+        }
+    }
+    for body in remaining.into_values() {
+        let entity = match &body.kind {
+            BodyKind::Planet(source) => spawn_planet(&mut commands, source.clone()),
+            BodyKind::Probe => spawn_probe(&mut commands, &probe_assets, body.translation, body.velocity),
+            BodyKind::Particle => commands.spawn(Particle::new(body.translation, body.velocity, body.radius, &particle_assets)).id(),
+            BodyKind::Ship(ship) => spawn_player_ship(&mut commands, &scene_assets, body.translation, body.velocity, body.radius, *ship),
+        };
+        let mut transform = Transform::from_translation(body.translation).with_rotation(body.rotation);
+        if matches!(body.kind, BodyKind::Particle | BodyKind::Probe) {
+            transform.scale = Vec3::splat(body.radius);
+        }
+        let mut entity = commands.entity(entity);
+        entity.insert((body.id, transform, MotionComp {
+            velocity: body.velocity,
+            acceleration: Vec3::ZERO,
+            angular_velocity: body.angular_velocity,
+        }));
+        if let Some(mass) = body.mass {
+            entity.insert(GravitationComp::new(mass));
+        }
+        if body.frozen {
+            entity.insert(Escaped).remove::<(MotionComp, GravitationComp, CollisionDetection)>();
+        }
+    }
+    simulation_time.tick = snapshot.tick;
+    simulation_time.elapsed = snapshot.time;
+    let (mut puzzle, mut ship_controls, mut maneuver_plan, mut timeline) = session;
+    *puzzle = snapshot.puzzle.clone();
+    *ship_controls = snapshot.ship_controls;
+    *maneuver_plan = snapshot.maneuver_plan.clone();
+    *timeline = snapshot.timeline.clone();
+}
+
+fn clear_snapshots(mut history: ResMut<SnapshotHistory>) {
+    history.snapshots.clear();
+    history.version += 1;
+}
+
+fn spawn_timeline(mut commands: Commands) {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(10.),
+                left: Val::Px(10.),
+                width: Val::Px(400.),
+                height: Val::Px(16.),
+                ..default()
+            },
+            background_color: Color::srgba(1.0, 1.0, 1.0, 0.1).into(),
+            ..default()
+        },
+        Timeline,
+    ));
+}
+
+// 每个快照对应时间轴上的一段，点击后回退到该快照并暂停，可继续点击其他快照，按 Esc 从当前快照继续运行
+fn update_timeline(
+    mut commands: Commands,
+    timelines: Query<Entity, With<Timeline>>,
+    history: Res<SnapshotHistory>,
+    mut shown_version: Local<u64>,
+) {
+    if *shown_version == history.version { return; }
+    *shown_version = history.version;
+    let Ok(timeline) = timelines.get_single() else { return; };
+    commands.entity(timeline).despawn_descendants();
+    let width = 100.0 / SNAPSHOT_CAPACITY as f32;
+    commands.entity(timeline).with_children(|parent| {
+        for snapshot in history.snapshots.iter() {
+            parent.spawn((
+                ButtonBundle {
+                    style: Style {
+                        width: Val::Percent(width),
+                        height: Val::Percent(100.),
+                        border: UiRect::right(Val::Px(1.)),
+                        ..default()
+                    },
+                    background_color: Color::srgba(0.4, 0.7, 1.0, 0.6).into(),
+                    border_color: Color::BLACK.into(),
+                    ..default()
+                },
+                TimelineSegment(snapshot.tick),
+            ));
+        }
+    });
+}
+
+fn handle_timeline_click(
+    segments: Query<(&Interaction, &TimelineSegment), Changed<Interaction>>,
+    mut rewind_events: EventWriter<RewindEvent>,
+    mut next_state: ResMut<NextState<RunningState>>,
+) {
+    for (interaction, segment) in segments.iter() {
+        if *interaction != Interaction::Pressed { continue; }
+        rewind_events.send(RewindEvent { tick: segment.0 });
+        next_state.set(RunningState::Paused);
+    }
+}
+
+fn spawn_rewind_button(mut commands: Commands) {
+    commands.spawn((
+        ButtonBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(40.),
+                left: Val::Percent(45.),
+                padding: UiRect::all(Val::Px(8.)),
+                ..default()
+            },
+            background_color: Color::srgba(1.0, 1.0, 1.0, 0.15).into(),
+            ..default()
+        },
+        RewindButton,
+        StateScoped(RunningState::End),
+    )).with_children(|parent| {
+        parent.spawn(TextBundle::from_section(
+            format!("Rewind {} s", END_SCREEN_REWIND),
+            TextStyle {
+                font_size: 24.,
+                color: Color::WHITE,
+                ..default()
+            }
+        ));
+    });
+}
+
+fn handle_rewind_button(
+    interactions: Query<&Interaction, (Changed<Interaction>, With<RewindButton>)>,
+    history: Res<SnapshotHistory>,
+    simulation_time: Res<SimulationTime>,
+    mut rewind_events: EventWriter<RewindEvent>,
+    mut next_state: ResMut<NextState<RunningState>>,
+) {
+    for interaction in interactions.iter() {
+        if *interaction != Interaction::Pressed { continue; }
+        let target = simulation_time.elapsed - END_SCREEN_REWIND;
+        let snapshot = history.snapshots.iter().rev().find(|snapshot| snapshot.time <= target)
+            .or(history.snapshots.front());
+        if let Some(snapshot) = snapshot {
+            rewind_events.send(RewindEvent { tick: snapshot.tick });
+            next_state.set(RunningState::Paused);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+    use crate::gravity_system::{planet::PlanetSpawn, scenario::PlanetJson};
+
+    fn test_world() -> World {
+        let mut world = World::new();
+        world.init_resource::<Assets<Mesh>>();
+        world.init_resource::<Assets<StandardMaterial>>();
+        world.init_resource::<ParticleAssets>();
+        world.init_resource::<ProbeAssets>();
+        world.init_resource::<PuzzleState>();
+        world.init_resource::<ShipControls>();
+        world.init_resource::<ManeuverPlan>();
+        world.init_resource::<TimelineState>();
+        world.init_resource::<SceneAssets>();
+        world.init_resource::<Scenario>();
+        world.init_resource::<SimulationTime>();
+        world.init_resource::<SnapshotHistory>();
+        world.init_resource::<Events<RewindEvent>>();
+        world
+    }
+
+    fn planet_source() -> PlanetSource {
+        let planet: PlanetJson = serde_json::from_str(r#"{ "name": "Moon", "mass": 2.0, "radius": 1.5 }"#).unwrap();
+        PlanetSource(Arc::new(PlanetSpawn { planet, scene: Handle::default(), emissive: None, star: false }))
+    }
+
+    #[test]
+    fn restore_returns_bodies_and_time_to_snapshot() {
+        let mut world = test_world();
+        let moon = world.spawn((
+            BodyId(1),
+            Transform::from_xyz(10.0, 0.0, 0.0),
+            MotionComp { velocity: Vec3::new(0.0, 2.0, 0.0), ..default() },
+            GravitationComp::new(2.0),
+            CollisionDetection { radius: 1.5 },
+            planet_source(),
+        )).id();
+        let sun = world.spawn((
+            BodyId(2),
+            Transform::default(),
+            MotionComp::default(),
+            GravitationComp::new(100.0),
+            CollisionDetection { radius: 3.0 },
+            planet_source(),
+        )).id();
+        world.run_system_once(take_snapshot);
+        // 继续运行，期间一个天体移动，另一个被碰撞移除
+        *world.resource_mut::<SimulationTime>() = SimulationTime { elapsed: 3.0, tick: 40 };
+        world.entity_mut(sun).insert((Transform::from_xyz(1.0, 1.0, 0.0), MotionComp { velocity: Vec3::X, ..default() }));
+        world.despawn(moon);
+        world.send_event(RewindEvent { tick: 0 });
+        world.run_system_once(restore_snapshot);
+        let mut bodies = world.query::<(&BodyId, &Transform, &MotionComp, &GravitationComp)>();
+        let mut restored: Vec<_> = bodies.iter(&world)
+            .map(|(id, transform, motion, gravitation)| (*id, transform.translation, motion.velocity, gravitation.mass))
+            .collect();
+        restored.sort_by_key(|(id, ..)| *id);
+        assert_eq!(restored, vec![
+            (BodyId(1), Vec3::new(10.0, 0.0, 0.0), Vec3::new(0.0, 2.0, 0.0), 2.0),
+            (BodyId(2), Vec3::ZERO, Vec3::ZERO, 100.0),
+        ]);
+        let simulation_time = world.resource::<SimulationTime>();
+        assert_eq!((simulation_time.elapsed, simulation_time.tick), (0.0, 0));
+    }
+
+    #[test]
+    fn restore_brings_back_probe_and_puzzle_progress() {
+        let mut world = test_world();
+        let probe = world.run_system_once(|mut commands: Commands, probe_assets: Res<ProbeAssets>| {
+            spawn_probe(&mut commands, &probe_assets, Vec3::new(5.0, 0.0, 0.0), Vec3::Z)
+        });
+        world.entity_mut(probe).insert(BodyId(3));
+        world.resource_mut::<PuzzleState>().used = 4.0;
+        world.run_system_once(take_snapshot);
+        // 探测器坠毁，关卡判负
+        world.despawn(probe);
+        world.resource_mut::<PuzzleState>().used = 9.0;
+        world.send_event(RewindEvent { tick: 0 });
+        world.run_system_once(restore_snapshot);
+        let mut probes = world.query_filtered::<(&BodyId, &Transform, &MotionComp), With<Probe>>();
+        let (id, transform, motion) = probes.single(&world);
+        assert_eq!((*id, transform.translation, motion.velocity), (BodyId(3), Vec3::new(5.0, 0.0, 0.0), Vec3::Z));
+        assert_eq!(world.resource::<PuzzleState>().used, 4.0);
+    }
+
+    #[test]
+    fn restore_returns_ship_fuel_and_controls() {
+        let mut world = test_world();
+        let ship = PlayerShip { dry_mass: 1.0, fuel: 10.0, exhaust_velocity: 5.0, max_thrust: 2.0 };
+        let entity = world.run_system_once(move |mut commands: Commands, scene_assets: Res<SceneAssets>| {
+            spawn_player_ship(&mut commands, &scene_assets, Vec3::X, Vec3::Y, 0.5, ship)
+        });
+        world.entity_mut(entity).insert(BodyId(4));
+        world.resource_mut::<ShipControls>().throttle = 0.5;
+        world.run_system_once(take_snapshot);
+        // 全力点火后燃料减少
+        world.entity_mut(entity).get_mut::<PlayerShip>().unwrap().fuel = 2.0;
+        world.resource_mut::<ShipControls>().throttle = 1.0;
+        world.send_event(RewindEvent { tick: 0 });
+        world.run_system_once(restore_snapshot);
+        assert_eq!(world.entity(entity).get::<PlayerShip>().unwrap().fuel, 10.0);
+        assert_eq!(world.entity(entity).get::<GravitationComp>().unwrap().mass, 11.0);
+        assert_eq!(world.resource::<ShipControls>().throttle, 0.5);
+    }
+
+    #[test]
+    fn history_evicts_oldest_and_truncates_abandoned_branch() {
+        let snapshot = |tick| Snapshot {
+            tick,
+            time: tick as f64,
+            bodies: Vec::new(),
+            puzzle: default(),
+            ship_controls: default(),
+            maneuver_plan: default(),
+            timeline: default(),
+        };
+        let mut history = SnapshotHistory::default();
+        for index in 0..SNAPSHOT_CAPACITY as u64 + 5 {
+            history.push(snapshot(index * SNAPSHOT_INTERVAL_TICKS));
+        }
+        assert_eq!(history.snapshots.len(), SNAPSHOT_CAPACITY);
+        assert_eq!(history.snapshots.front().unwrap().tick, 5 * SNAPSHOT_INTERVAL_TICKS);
+        // 回退后从较早的时刻继续运行
+        history.push(snapshot(10 * SNAPSHOT_INTERVAL_TICKS));
+        assert_eq!(history.snapshots.back().unwrap().tick, 10 * SNAPSHOT_INTERVAL_TICKS);
+        assert_eq!(history.snapshots.len(), 6);
+    }
+}
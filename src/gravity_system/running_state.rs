@@ -1,7 +1,11 @@
 use bevy::input::common_conditions::input_just_pressed;
 use bevy::prelude::*;
 
-#[derive(States, Clone, Copy, Default, Eq, PartialEq, Hash, Debug)]
+use crate::loading_state::AppState;
+
+/// 资源加载完成（AppState::Running）后才存在
+#[derive(SubStates, Clone, Copy, Default, Eq, PartialEq, Hash, Debug)]
+#[source(AppState = AppState::Running)]
 pub enum RunningState {
     #[default]
     Running,
@@ -13,16 +17,40 @@ pub enum RunningState {
 #[derive(Event)]
 pub struct ResetEvent;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunResult {
+    Won,
+    Lost,
+}
+
+/// 结算界面显示的内容，由让模拟进入 End 的系统填写，离开 End 时清空
+#[derive(Resource, Debug, Clone, Default)]
+pub struct RunOutcome {
+    /// 沙盒模式下为 None
+    pub result: Option<RunResult>,
+    pub reason: String,
+    /// 1 到 3 星，仅在关卡胜利时给出
+    pub stars: Option<u8>,
+    pub details: Vec<String>,
+}
+
+#[derive(Component)]
+struct RetryButton;
+
 pub struct RunningStatePlugin;
 impl Plugin for RunningStatePlugin {
     fn build(&self, app: &mut App) {
-        app.init_state::<RunningState>().enable_state_scoped_entities::<RunningState>();
+        app.add_sub_state::<RunningState>().enable_state_scoped_entities::<RunningState>();
         app.add_event::<ResetEvent>();
-        app.add_systems(Update, handle_reset_operation);
-        app.add_systems(Update, switch_running_and_paused.run_if(input_just_pressed(KeyCode::Escape)));
+        app.init_resource::<RunOutcome>();
+        app.add_systems(Update, handle_reset_operation.run_if(in_state(AppState::Running)));
+        app.add_systems(Update, switch_running_and_paused
+            .run_if(in_state(AppState::Running).and_then(input_just_pressed(KeyCode::Escape))));
         app.add_systems(OnEnter(RunningState::Paused), spawn_status_text("Paused", RunningState::Paused));
         app.add_systems(OnEnter(RunningState::Resetting), spawn_status_text("Resetting...", RunningState::Resetting));
-        app.add_systems(OnEnter(RunningState::End), spawn_status_text("End", RunningState::End));
+        app.add_systems(OnEnter(RunningState::End), spawn_results_screen);
+        app.add_systems(OnExit(RunningState::End), |mut outcome: ResMut<RunOutcome>| *outcome = RunOutcome::default());
+        app.add_systems(Update, handle_retry_button.run_if(in_state(RunningState::End)));
     }
 }
 
@@ -89,4 +117,69 @@ fn spawn_status_text(text: &'static str, running_state: RunningState) -> impl Fn
         let text_entity = commands.spawn(text_bundle).id();
         commands.spawn(node_bundle).add_child(text_entity);
     }
-}
\ No newline at end of file
+}
+fn spawn_results_screen(mut commands: Commands, outcome: Res<RunOutcome>) {
+    let (title, color) = match outcome.result {
+        Some(RunResult::Won) => ("Success", Color::srgb(0.5, 1.0, 0.5)),
+        Some(RunResult::Lost) => ("Failed", Color::srgb(1.0, 0.45, 0.4)),
+        None => ("End", Color::WHITE),
+    };
+    let line_style = TextStyle {
+        font_size: 28.,
+        color: Color::WHITE,
+        ..default()
+    };
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(8.),
+                height: Val::Percent(100.),
+                width: Val::Percent(100.),
+                ..default()
+            },
+            ..default()
+        },
+        StateScoped(RunningState::End),
+    )).with_children(|parent| {
+        parent.spawn(TextBundle::from_section(title, TextStyle { font_size: 140., color, ..default() }));
+        if let Some(stars) = outcome.stars {
+            let stars = (1..=3).map(|star| if star <= stars { '★' } else { '☆' }).collect::<String>();
+            parent.spawn(TextBundle::from_section(stars, TextStyle { font_size: 64., color: Color::srgb(1.0, 0.85, 0.3), ..default() }));
+        }
+        if !outcome.reason.is_empty() {
+            parent.spawn(TextBundle::from_section(outcome.reason.clone(), line_style.clone()));
+        }
+        for line in outcome.details.iter() {
+            parent.spawn(TextBundle::from_section(line.clone(), line_style.clone()));
+        }
+        parent.spawn((
+            ButtonBundle {
+                style: Style {
+                    margin: UiRect::top(Val::Px(16.)),
+                    padding: UiRect::all(Val::Px(8.)),
+                    ..default()
+                },
+                background_color: Color::srgba(1.0, 1.0, 1.0, 0.15).into(),
+                ..default()
+            },
+            RetryButton,
+        )).with_children(|parent| {
+            parent.spawn(TextBundle::from_section("Retry", TextStyle { font_size: 24., ..line_style }));
+        });
+    });
+}
+
+fn handle_retry_button(
+    interactions: Query<&Interaction, (Changed<Interaction>, With<RetryButton>)>,
+    mut reset_event_writer: EventWriter<ResetEvent>,
+    mut next_state: ResMut<NextState<RunningState>>,
+) {
+    for interaction in interactions.iter() {
+        if *interaction != Interaction::Pressed { continue; }
+        reset_event_writer.send(ResetEvent);
+        next_state.set(RunningState::Running);
+    }
+}
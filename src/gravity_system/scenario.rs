@@ -0,0 +1,681 @@
+use std::f32::consts::TAU;
+use std::fs;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::asset_loader::EmissiveJson;
+use crate::loading_state::LoadingAssets;
+
+use super::{
+    generator::{generate_and_save, GeneratorSettings},
+    gravitation::barycenter,
+    horizons::import,
+    orbit::OrbitalElements,
+    units::UnitSystem,
+};
+
+const FIXED_STARS_PATH: &str = "assets/json/fixed_stars.json";
+const PLANETS_PATH: &str = "assets/json/planets.json";
+const HORIZONS_SCENARIO_PATH: &str = "assets/json/scenarios/generated/horizons.json";
+const LEVELS_DIR: &str = "assets/json/levels";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+pub struct Vec3Json {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+impl From<Vec3Json> for Vec3 {
+    fn from(Vec3Json { x, y, z }: Vec3Json) -> Self {
+        Vec3::new(x, y, z)
+    }
+}
+impl From<Vec3> for Vec3Json {
+    fn from(Vec3 { x, y, z }: Vec3) -> Self {
+        Self { x, y, z }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PlanetJson {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub mass: f32,
+    /// 指定 `parent` 时为相对父天体的位置与速度
+    #[serde(default)]
+    pub position: Vec3Json,
+    #[serde(default)]
+    pub velocity: Vec3Json,
+    pub radius: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    /// 相对 `parent` 的轨道，给出后忽略 position 与 velocity
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub orbit: Option<OrbitJson>,
+    /// 未给出时按名称和位置确定性地随机自转
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spin: Option<SpinJson>,
+    /// model_catalog.json 中的模型名，未给出时按名称确定性地随机挑选
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// 覆盖模型材质的自发光，未给出时使用目录中该模型的设置
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emissive: Option<EmissiveJson>,
+    /// 仅对恒星生效，未给出的项由质量推算
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub light: Option<StarLightJson>,
+}
+
+impl PlanetJson {
+    /// 相对父天体的位置与速度，给出轨道时由轨道根数换算，轨道无效时返回 None
+    pub fn relative_state(&self, parent_mass: f32, gravitational_constant: f32) -> Option<(Vec3, Vec3)> {
+        match &self.orbit {
+            Some(orbit) => {
+                let mu = gravitational_constant * (parent_mass + self.mass);
+                orbit.to_elements(mu).and_then(|elements| elements.to_state_vectors(mu))
+            }
+            None => Some((self.position.into(), self.velocity.into())),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+pub struct StarLightJson {
+    /// 线性 RGB
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<[f32; 3]>,
+    /// 相对光度，场景中质量最大的恒星由质量推算为 1
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub luminosity: Option<f32>,
+}
+
+/// 自转轴由 y 轴绕 x 轴倾斜 `axial_tilt` 度得到
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SpinJson {
+    /// 自转周期，负值表示逆向自转，0 表示不自转
+    pub period: f32,
+    #[serde(default)]
+    pub axial_tilt: f32,
+}
+impl SpinJson {
+    pub fn angular_velocity(&self) -> Vec3 {
+        if self.period == 0.0 { return Vec3::ZERO; }
+        Quat::from_rotation_x(self.axial_tilt.to_radians()) * Vec3::Y * (TAU / self.period)
+    }
+}
+
+/// 开启后，近距离天体的自转在潮汐力矩作用下趋向与公转同步
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TidesJson {
+    /// 潮汐力矩系数，对应 3k₂ / (CQ)
+    #[serde(default = "default_tidal_strength")]
+    pub strength: f32,
+}
+fn default_tidal_strength() -> f32 {
+    1.0
+}
+
+/// 逃逸判定：相对系统质心为双曲轨道且距离超过 `radius` 时视为逃逸
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EscapeJson {
+    #[serde(default = "default_escape_radius")]
+    pub radius: f32,
+    #[serde(default)]
+    pub policy: EscapePolicy,
+}
+impl Default for EscapeJson {
+    fn default() -> Self {
+        Self {
+            radius: default_escape_radius(),
+            policy: default(),
+        }
+    }
+}
+fn default_escape_radius() -> f32 {
+    1000.0
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EscapePolicy {
+    Despawn,
+    /// 停止模拟，保留在原处
+    Freeze,
+    #[default]
+    Keep,
+}
+
+/// 从 `observer` 观察恒星的凌星、食与掩星
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EclipsesJson {
+    /// 观察者天体的名称
+    pub observer: String,
+    /// 光变曲线 CSV 的输出路径
+    #[serde(default = "default_light_curve_path")]
+    pub light_curve: String,
+}
+fn default_light_curve_path() -> String {
+    "output/light_curve.csv".to_string()
+}
+
+/// 为指定天体记录轨迹，`bodies` 为空时记录所有天体
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TrajectoriesJson {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bodies: Vec<String>,
+    /// 每隔多少个固定步采样一次
+    #[serde(default = "default_trajectory_interval")]
+    pub interval: u64,
+}
+fn default_trajectory_interval() -> u64 {
+    8
+}
+
+/// 玩家驾驶的飞船，推力加速度为推力除以干重与剩余燃料之和
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ShipJson {
+    /// 给出时 position 与 velocity 相对该天体
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    #[serde(default)]
+    pub position: Vec3Json,
+    #[serde(default)]
+    pub velocity: Vec3Json,
+    pub dry_mass: f32,
+    pub fuel_mass: f32,
+    /// 排气速度，燃料消耗率为推力除以该值
+    pub exhaust_velocity: f32,
+    pub max_thrust: f32,
+    #[serde(default = "default_ship_radius")]
+    pub radius: f32,
+}
+fn default_ship_radius() -> f32 {
+    1.0
+}
+
+/// 时间线上的一项：`at` 与 `when` 至少给出一个，都给出时在该时间之后条件首次成立时执行，每项只执行一次
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TimelineEventJson {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub at: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when: Option<ConditionJson>,
+    pub action: ActionJson,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum ConditionJson {
+    CloserThan { bodies: [String; 2], distance: f32 },
+    FartherThan { bodies: [String; 2], distance: f32 },
+    SpeedAbove { body: String, speed: f32 },
+    /// 天体出现过之后被碰撞、瓦解或逃逸移除
+    Removed { body: String },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionJson {
+    DeltaV { body: String, delta_v: Vec3Json },
+    /// 有 parent 时相对该天体当时的状态生成，否则使用场景坐标
+    Spawn {
+        body: PlanetJson,
+        #[serde(default)]
+        star: bool,
+    },
+    SetMass { body: String, mass: f32 },
+    Pause { message: String },
+    Focus { body: String },
+}
+
+/// 引力弹弓关卡：从 `start` 发射探测器，在 Δv 预算内抵达目标
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PuzzleJson {
+    /// 发射探测器的天体名称
+    pub start: String,
+    pub goal: GoalJson,
+    pub delta_v_budget: f32,
+    /// 超过该模拟时间仍未抵达即失败
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_limit: Option<f64>,
+    pub stars: StarsJson,
+}
+
+/// 进入以 `body` 天体或 `center` 为圆心、`radius` 为半径的范围即抵达；两者都给出时以天体为准
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GoalJson {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub center: Option<Vec3Json>,
+    pub radius: f32,
+}
+
+/// 抵达即得一星，同时满足 `two` 或 `three` 中的 Δv 与用时上限时得两星或三星
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StarsJson {
+    pub two: StarThresholdJson,
+    pub three: StarThresholdJson,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct StarThresholdJson {
+    pub delta_v: f32,
+    pub time: f64,
+}
+
+/// 在牛顿引力之外叠加一阶后牛顿（EIH）修正，按 G 键切换
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+pub struct PostNewtonianJson {
+    /// 以场景单位制表示的光速，未给出时使用真实光速；调小可放大近日点进动
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speed_of_light: Option<f32>,
+}
+
+/// 开启后，进入洛希极限的小天体会瓦解为测试粒子
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TidalDisruptionJson {
+    #[serde(default = "default_fragment_count")]
+    pub fragments: u32,
+}
+fn default_fragment_count() -> u32 {
+    300
+}
+impl Default for TidalDisruptionJson {
+    fn default() -> Self {
+        Self { fragments: default_fragment_count() }
+    }
+}
+
+/// 开普勒轨道根数，角度单位为度
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct OrbitJson {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub semi_major_axis: Option<f32>,
+    /// 未给出半长轴时由周期换算
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub period: Option<f32>,
+    #[serde(default)]
+    pub eccentricity: f32,
+    #[serde(default)]
+    pub inclination: f32,
+    #[serde(default)]
+    pub ascending_node: f32,
+    #[serde(default)]
+    pub argument_of_periapsis: f32,
+    #[serde(default)]
+    pub true_anomaly: f32,
+}
+impl OrbitJson {
+    fn to_elements(&self, mu: f32) -> Option<OrbitalElements> {
+        let semi_major_axis = self.semi_major_axis
+            .or_else(|| self.period.map(|period| OrbitalElements::semi_major_axis_from_period(period, mu)))?;
+        Some(OrbitalElements {
+            semi_major_axis,
+            eccentricity: self.eccentricity,
+            inclination: self.inclination.to_radians(),
+            ascending_node: self.ascending_node.to_radians(),
+            argument_of_periapsis: self.argument_of_periapsis.to_radians(),
+            true_anomaly: self.true_anomaly.to_radians(),
+        })
+    }
+}
+
+/// 由测试粒子组成的环，粒子在 `center` 天体周围做圆轨道运动
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RingJson {
+    /// 环绕的天体名称，为空时环绕原点，中心质量取场景中所有天体质量之和
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub center: Option<String>,
+    pub inner_radius: f32,
+    pub outer_radius: f32,
+    pub count: u32,
+    /// 环平面的法向，默认为 y 轴
+    #[serde(default = "default_ring_normal")]
+    pub normal: Vec3Json,
+    #[serde(default)]
+    pub thickness: f32,
+    #[serde(default = "default_particle_radius")]
+    pub particle_radius: f32,
+    #[serde(default)]
+    pub seed: u64,
+}
+fn default_ring_normal() -> Vec3Json {
+    Vec3::Y.into()
+}
+fn default_particle_radius() -> f32 {
+    0.3
+}
+
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Scenario {
+    /// 质量、位置、速度等数值所用的单位制
+    #[serde(default)]
+    pub units: UnitSystem,
+    #[serde(default)]
+    pub fixed_stars: Vec<PlanetJson>,
+    #[serde(default)]
+    pub planets: Vec<PlanetJson>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rings: Vec<RingJson>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tides: Option<TidesJson>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tidal_disruption: Option<TidalDisruptionJson>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_newtonian: Option<PostNewtonianJson>,
+    #[serde(default)]
+    pub escape: EscapeJson,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eclipses: Option<EclipsesJson>,
+    /// 任意两天体距离小于该值时记录近距离接触
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub close_approach_distance: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trajectories: Option<TrajectoriesJson>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub puzzle: Option<PuzzleJson>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ship: Option<ShipJson>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub timeline: Vec<TimelineEventJson>,
+    /// 生成天体前把质心平移到原点并消去总动量
+    #[serde(default)]
+    pub barycentric: bool,
+}
+
+impl Scenario {
+    /// `--scenario <path>` 指定场景文件，`--level <name>` 载入 assets/json/levels 下的关卡，`--generate` 生成随机星系，
+    /// `--horizons <path> [--units si|au|nbody]` 导入 Horizons 向量表，否则使用 fixed_stars.json 与 planets.json；
+    /// `--barycentric` 对任一来源的场景开启质心修正。读取失败的文件记录到加载界面
+    pub fn from_args(loading: &mut LoadingAssets) -> Self {
+        let args: Vec<String> = std::env::args().collect();
+        let mut scenario = Self::load_from_args(&args, loading);
+        scenario.barycentric |= args.iter().any(|arg| arg == "--barycentric");
+        scenario
+    }
+
+    fn load_from_args(args: &[String], loading: &mut LoadingAssets) -> Self {
+        if args.iter().any(|arg| arg == "--generate") {
+            return generate_and_save(&GeneratorSettings::from_args(args));
+        }
+        let horizons_paths: Vec<&str> = args.windows(2)
+            .filter(|pair| pair[0] == "--horizons")
+            .map(|pair| pair[1].as_str())
+            .collect();
+        if !horizons_paths.is_empty() {
+            let units = arg_value(args, "--units").and_then(UnitSystem::from_arg).unwrap_or(UnitSystem::Astronomical);
+            match import(&horizons_paths, units) {
+                Ok(scenario) => {
+                    if let Err(err) = scenario.save(HORIZONS_SCENARIO_PATH) {
+                        error!("failed to save imported scenario to {}: {}", HORIZONS_SCENARIO_PATH, err);
+                    }
+                    return scenario;
+                }
+                Err(err) => loading.fail(horizons_paths.join(", "), format!("failed to import Horizons vectors: {}", err)),
+            }
+        }
+        let level = arg_value(args, "--level").map(|name| format!("{}/{}.json", LEVELS_DIR, name));
+        let scenario = match level.as_deref().or(arg_value(args, "--scenario")) {
+            Some(path) => Self::from_file(path).or_else(|err| {
+                loading.fail(path, format!("{}, falling back to the default scenario", err));
+                Self::from_default_files()
+            }),
+            None => Self::from_default_files(),
+        };
+        scenario.unwrap_or_else(|err| {
+            loading.fail(format!("{}, {}", FIXED_STARS_PATH, PLANETS_PATH), err);
+            default()
+        })
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let scenario = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let scenario = serde_json::from_str::<Scenario>(&scenario).map_err(|err| err.to_string())?;
+        scenario.resolve_orbits()?.validate_timeline()?.validate_tidal_disruption()
+    }
+
+    pub fn from_default_files() -> Result<Self, String> {
+        let scenario = Self {
+            fixed_stars: get_planets_from_json(FIXED_STARS_PATH)?,
+            planets: get_planets_from_json(PLANETS_PATH)?,
+            ..default()
+        };
+        scenario.resolve_orbits()
+    }
+
+    /// 把以父天体和轨道根数描述的天体换算为绝对位置与速度，
+    /// 按依赖顺序求解，因此卫星的父天体也可以是另一颗行星
+    fn resolve_orbits(mut self) -> Result<Self, String> {
+        let gravitational_constant = self.units.gravitational_constant();
+        let bodies: Vec<&PlanetJson> = self.fixed_stars.iter().chain(self.planets.iter()).collect();
+        let mut states = vec![None; bodies.len()];
+        for index in 0..bodies.len() {
+            resolve_state(index, &bodies, gravitational_constant, &mut states, &mut Vec::new())?;
+        }
+        for (planet, state) in self.fixed_stars.iter_mut().chain(self.planets.iter_mut()).zip(states) {
+            let (position, velocity) = state.unwrap();
+            planet.position = position.into();
+            planet.velocity = velocity.into();
+            planet.parent = None;
+            planet.orbit = None;
+        }
+        Ok(self)
+    }
+
+    /// 瓦解至少要产生一个碎片，否则天体会连同质量一起消失
+    fn validate_tidal_disruption(self) -> Result<Self, String> {
+        match &self.tidal_disruption {
+            Some(policy) if policy.fragments == 0 => Err("tidal_disruption.fragments must be at least 1".to_string()),
+            _ => Ok(self),
+        }
+    }
+
+    /// 时间线引用的天体须在场景中或由更早的项生成，数值须有效
+    fn validate_timeline(self) -> Result<Self, String> {
+        let mut bodies: Vec<(&str, f32)> = self.fixed_stars.iter().chain(self.planets.iter())
+            .filter_map(|planet| planet.name.as_deref().map(|name| (name, planet.mass)))
+            .collect();
+        if let Some(ship) = &self.ship {
+            bodies.push(("Ship", ship.dry_mass + ship.fuel_mass));
+        }
+        let gravitational_constant = self.units.gravitational_constant();
+        for (index, event) in self.timeline.iter().enumerate() {
+            let fail = |message: String| format!("timeline[{}]: {}", index, message);
+            let find = |name: &str| bodies.iter().find(|(body, _)| *body == name).map(|(_, mass)| *mass)
+                .ok_or_else(|| fail(format!("unknown body {}", name)));
+            match event.at {
+                Some(at) if !(at.is_finite() && at >= 0.0) => return Err(fail(format!("invalid time {}", at))),
+                None if event.when.is_none() => return Err(fail("needs `at` or `when`".to_string())),
+                _ => (),
+            }
+            match &event.when {
+                Some(ConditionJson::CloserThan { bodies: pair, distance } | ConditionJson::FartherThan { bodies: pair, distance }) => {
+                    find(&pair[0])?;
+                    find(&pair[1])?;
+                    if !(distance.is_finite() && *distance > 0.0) { return Err(fail(format!("invalid distance {}", distance))); }
+                }
+                Some(ConditionJson::SpeedAbove { body, speed }) => {
+                    find(body)?;
+                    if !(speed.is_finite() && *speed >= 0.0) { return Err(fail(format!("invalid speed {}", speed))); }
+                }
+                Some(ConditionJson::Removed { body }) => { find(body)?; }
+                None => (),
+            }
+            match &event.action {
+                ActionJson::DeltaV { body, .. } | ActionJson::Focus { body } => { find(body)?; }
+                ActionJson::SetMass { body, mass } => {
+                    find(body)?;
+                    if !(mass.is_finite() && *mass >= 0.0) { return Err(fail(format!("invalid mass {}", mass))); }
+                }
+                ActionJson::Spawn { body, .. } => {
+                    let name = body.name.as_deref().ok_or_else(|| fail("spawned bodies need a name".to_string()))?;
+                    if find(name).is_ok() { return Err(fail(format!("body {} already exists", name))); }
+                    if !(body.mass >= 0.0 && body.radius > 0.0) {
+                        return Err(fail(format!("invalid mass or radius of body {}", name)));
+                    }
+                    let parent_mass = match &body.parent {
+                        Some(parent) => find(parent)?,
+                        None if body.orbit.is_some() => return Err(fail(format!("body {} has an orbit but no parent", name))),
+                        None => 0.0,
+                    };
+                    if body.relative_state(parent_mass, gravitational_constant).is_none() {
+                        return Err(fail(format!("invalid orbit of body {}", name)));
+                    }
+                    bodies.push((name, body.mass));
+                }
+                ActionJson::Pause { .. } => (),
+            }
+        }
+        Ok(self)
+    }
+
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        if let Some(dir) = std::path::Path::new(path).parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn find_body(&self, name: &str) -> Option<&PlanetJson> {
+        self.fixed_stars.iter().chain(self.planets.iter())
+            .find(|planet| planet.name.as_deref() == Some(name))
+    }
+
+    /// 平移到质心系：质心位于原点且总动量为零
+    pub fn to_barycentric_frame(&self) -> Self {
+        let mut scenario = self.clone();
+        let bodies = scenario.fixed_stars.iter().chain(scenario.planets.iter())
+            .map(|planet| (planet.position.into(), planet.velocity.into(), planet.mass));
+        if let Some((center, center_velocity, _)) = barycenter(bodies) {
+            for planet in scenario.fixed_stars.iter_mut().chain(scenario.planets.iter_mut()) {
+                planet.position = (Vec3::from(planet.position) - center).into();
+                planet.velocity = (Vec3::from(planet.velocity) - center_velocity).into();
+            }
+            // 有 parent 的飞船随其天体平移
+            if let Some(ship) = scenario.ship.as_mut().filter(|ship| ship.parent.is_none()) {
+                ship.position = (Vec3::from(ship.position) - center).into();
+                ship.velocity = (Vec3::from(ship.velocity) - center_velocity).into();
+            }
+        }
+        scenario
+    }
+}
+
+pub fn arg_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter().position(|arg| arg == name).and_then(|index| args.get(index + 1)).map(String::as_str)
+}
+
+fn resolve_state(
+    index: usize,
+    bodies: &[&PlanetJson],
+    gravitational_constant: f32,
+    states: &mut [Option<(Vec3, Vec3)>],
+    resolving: &mut Vec<usize>,
+) -> Result<(Vec3, Vec3), String> {
+    if let Some(state) = states[index] { return Ok(state); }
+    let body = bodies[index];
+    let name = body.name.as_deref().unwrap_or("<unnamed>");
+    if resolving.contains(&index) {
+        return Err(format!("cyclic parent chain at body {}", name));
+    }
+    let state = match &body.parent {
+        None if body.orbit.is_some() => return Err(format!("body {} has an orbit but no parent", name)),
+        None => (body.position.into(), body.velocity.into()),
+        Some(parent) => {
+            let parent_index = bodies.iter().position(|other| other.name.as_deref() == Some(parent))
+                .ok_or_else(|| format!("unknown parent {} of body {}", parent, name))?;
+            resolving.push(index);
+            let (parent_position, parent_velocity) = resolve_state(parent_index, bodies, gravitational_constant, states, resolving)?;
+            resolving.pop();
+            let (position, velocity) = body.relative_state(bodies[parent_index].mass, gravitational_constant)
+                .ok_or_else(|| format!("invalid orbit of body {}", name))?;
+            (parent_position + position, parent_velocity + velocity)
+        }
+    };
+    states[index] = Some(state);
+    Ok(state)
+}
+
+pub fn get_planets_from_json(path: &str) -> Result<Vec<PlanetJson>, String> {
+    let fixed_stars = fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+    serde_json::from_str::<Vec<PlanetJson>>(&fixed_stars).map_err(|err| format!("{}: {}", path, err))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  #[test]
+  fn it_works() {
+    let planets = get_planets_from_json("assets/json/fixed_stars.json");
+    println!("planets: {:?}", planets)
+  }
+  #[test]
+  fn ring_scenario_parses() {
+    let scenario = Scenario::from_file("assets/json/scenarios/ringed_planet.json").unwrap();
+    assert_eq!(scenario.rings.len(), 2);
+    assert!(scenario.find_body(scenario.rings[0].center.as_deref().unwrap()).is_some());
+  }
+  #[test]
+  fn nested_orbits_resolve_to_absolute_states() {
+    let scenario = Scenario::from_file("assets/json/scenarios/moons.json").unwrap();
+    let planet = scenario.find_body("Blue").unwrap();
+    let moon = scenario.find_body("Blue I").unwrap();
+    assert!(moon.parent.is_none() && moon.orbit.is_none());
+    let distance = Vec3::from(moon.position).distance(planet.position.into());
+    assert!((distance - 20.0).abs() < 1e-2);
+  }
+  #[test]
+  fn cyclic_parents_are_rejected() {
+    let scenario: Scenario = serde_json::from_str(r#"{
+      "planets": [
+        { "name": "A", "mass": 1.0, "radius": 1.0, "parent": "B" },
+        { "name": "B", "mass": 1.0, "radius": 1.0, "parent": "A" }
+      ]
+    }"#).unwrap();
+    assert!(scenario.resolve_orbits().is_err());
+  }
+  #[test]
+  fn transit_scenario_names_its_observer() {
+    let scenario = Scenario::from_file("assets/json/scenarios/transits.json").unwrap();
+    let eclipses = scenario.eclipses.as_ref().unwrap();
+    assert!(scenario.find_body(&eclipses.observer).is_some());
+  }
+  #[test]
+  fn levels_name_their_start_and_goal_bodies() {
+    for level in ["hohmann", "slingshot"] {
+      let scenario = Scenario::from_file(&format!("{}/{}.json", LEVELS_DIR, level)).unwrap();
+      let puzzle = scenario.puzzle.as_ref().unwrap();
+      assert!(scenario.find_body(&puzzle.start).is_some());
+      assert!(puzzle.goal.body.as_ref().map_or(puzzle.goal.center.is_some(), |body| scenario.find_body(body).is_some()));
+    }
+  }
+  #[test]
+  fn timeline_references_are_validated_on_load() {
+    let scenario = Scenario::from_file("assets/json/scenarios/timeline.json").unwrap();
+    assert_eq!(scenario.timeline.len(), 7);
+    let mut broken = scenario.clone();
+    broken.timeline[3].action = ActionJson::DeltaV { body: "Missing".to_string(), delta_v: Vec3::Y.into() };
+    assert!(broken.validate_timeline().unwrap_err().contains("Missing"));
+    let mut untriggered = scenario;
+    untriggered.timeline[0].at = None;
+    assert!(untriggered.validate_timeline().is_err());
+  }
+  #[test]
+  fn zero_tidal_fragments_are_rejected() {
+    let scenario: Scenario = serde_json::from_str(r#"{ "tidal_disruption": { "fragments": 0 } }"#).unwrap();
+    assert!(scenario.validate_tidal_disruption().is_err());
+    let scenario: Scenario = serde_json::from_str(r#"{ "tidal_disruption": {} }"#).unwrap();
+    assert_eq!(scenario.validate_tidal_disruption().unwrap().tidal_disruption.unwrap().fragments, 300);
+  }
+  #[test]
+  fn barycentric_frame_removes_drift() {
+    let scenario = Scenario::from_default_files().unwrap().to_barycentric_frame();
+    let bodies = scenario.fixed_stars.iter().chain(scenario.planets.iter())
+      .map(|planet| (planet.position.into(), planet.velocity.into(), planet.mass));
+    let (center, center_velocity, _) = barycenter(bodies).unwrap();
+    assert!(center.length() < 1e-2);
+    assert!(center_velocity.length() < 1e-2);
+  }
+}
@@ -0,0 +1,300 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::asset_loader::{FitToRadius, SceneAssets};
+use crate::loading_state::AppState;
+
+use super::{
+    collision_detection::{CollisionDetection, CollisionDetectionEvent},
+    gravitation::GravitationComp,
+    motion::MotionComp,
+    replay::replaying,
+    running_state::{ResetEvent, RunOutcome, RunningState},
+    scenario::Scenario,
+    units::UnitSystem,
+    GravityStatusUpdateSet,
+};
+
+// 姿态控制的最大角速度（弧度每单位时间）与油门每秒的变化量
+const ATTITUDE_RATE: f32 = 1.5;
+const THROTTLE_RATE: f32 = 0.5;
+const NAVBALL_RADIUS: f32 = 80.0;
+
+/// 玩家驾驶的飞船，质量为干重与剩余燃料之和
+#[derive(Component, Debug, Clone, Copy)]
+pub struct PlayerShip {
+    pub dry_mass: f32,
+    pub fuel: f32,
+    pub exhaust_velocity: f32,
+    pub max_thrust: f32,
+}
+
+impl PlayerShip {
+    pub fn mass(&self) -> f32 {
+        self.dry_mass + self.fuel
+    }
+
+    /// 以给定推力点火 dt，燃料不足时按剩余燃料折减推力，返回产生的加速度大小
+    pub fn burn(&mut self, thrust: f32, dt: f32) -> f32 {
+        let mut thrust = thrust;
+        let fuel_used = thrust / self.exhaust_velocity * dt;
+        if fuel_used > self.fuel {
+            thrust *= self.fuel / fuel_used;
+        }
+        self.fuel = (self.fuel - fuel_used).max(0.0);
+        thrust / self.mass()
+    }
+
+    /// 剩余燃料还能提供的 Δv（齐奥尔科夫斯基公式）
+    pub fn delta_v_budget(&self) -> f32 {
+        self.exhaust_velocity * (self.mass() / self.dry_mass).ln()
+    }
+
+    /// 瞬时冲量，返回燃料允许的实际 Δv
+    pub fn impulse(&mut self, delta_v: f32) -> f32 {
+        let delta_v = delta_v.min(self.delta_v_budget());
+        self.fuel = (self.fuel - self.mass() * (1.0 - (-delta_v / self.exhaust_velocity).exp())).max(0.0);
+        delta_v
+    }
+}
+
+/// 当前的操纵输入，`rotation` 为本体系下 (俯仰, 偏航, 滚转) 方向的角速度比例
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq)]
+pub struct ShipControls {
+    pub throttle: f32,
+    pub rotation: Vec3,
+}
+
+/// 相对主导引力源的顺行、轨道法向与径向朝外方向
+#[derive(Debug, Clone, Copy)]
+pub struct OrbitFrame {
+    pub prograde: Vec3,
+    pub normal: Vec3,
+    pub radial: Vec3,
+}
+
+pub struct ShipPlugin;
+impl Plugin for ShipPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ShipControls>();
+        app.add_systems(OnEnter(AppState::Running), spawn_ship);
+        app.add_systems(PostUpdate, (despawn_ship, spawn_ship).chain().run_if(on_event::<ResetEvent>()));
+        app.add_systems(Update, ship_control_input
+            .run_if(in_state(AppState::Running).and_then(ship_active).and_then(not(replaying))));
+        app.add_systems(Update, navball.run_if(in_state(AppState::Running).and_then(ship_active)));
+        // 在引力加速度求出之后叠加推力
+        app.add_systems(FixedUpdate, apply_ship_controls
+            .after(GravityStatusUpdateSet::AccelerationUpdate)
+            .before(GravityStatusUpdateSet::VelocityUpdate)
+            .run_if(in_state(RunningState::Running).and_then(super::replay::tick_allowed)));
+        app.add_systems(FixedUpdate, report_ship_collision
+            .in_set(GravityStatusUpdateSet::Analysis)
+            .run_if(on_event::<CollisionDetectionEvent>()));
+    }
+}
+
+pub fn ship_active(scenario: Res<Scenario>) -> bool {
+    scenario.ship.is_some()
+}
+
+fn spawn_ship(mut commands: Commands, scenario: Res<Scenario>, scene_assets: Res<SceneAssets>, mut controls: ResMut<ShipControls>) {
+    *controls = ShipControls::default();
+    let barycentric;
+    let scenario = if scenario.barycentric {
+        barycentric = scenario.to_barycentric_frame();
+        &barycentric
+    } else {
+        scenario.as_ref()
+    };
+    let Some(ship) = &scenario.ship else { return; };
+    let (mut position, mut velocity) = (Vec3::from(ship.position), Vec3::from(ship.velocity));
+    if let Some(parent) = ship.parent.as_deref() {
+        let Some(parent) = scenario.find_body(parent) else {
+            error!("ship parent {} not found", parent);
+            return;
+        };
+        position += Vec3::from(parent.position);
+        velocity += Vec3::from(parent.velocity);
+    }
+    spawn_player_ship(&mut commands, &scene_assets, position, velocity, ship.radius, PlayerShip {
+        dry_mass: ship.dry_mass,
+        fuel: ship.fuel_mass,
+        exhaust_velocity: ship.exhaust_velocity,
+        max_thrust: ship.max_thrust,
+    });
+}
+
+pub fn spawn_player_ship(commands: &mut Commands, scene_assets: &SceneAssets, position: Vec3, velocity: Vec3, radius: f32, ship: PlayerShip) -> Entity {
+    commands.spawn((
+        SceneBundle {
+            scene: scene_assets.spaceship.clone(),
+            transform: Transform::from_translation(position),
+            ..default()
+        },
+        FitToRadius(radius),
+        MotionComp {
+            velocity,
+            ..default()
+        },
+        GravitationComp::new(ship.mass()),
+        CollisionDetection { radius },
+        ship,
+        Name::new("Ship"),
+    )).id()
+}
+
+fn despawn_ship(mut commands: Commands, ships: Query<Entity, With<PlayerShip>>) {
+    for entity in ships.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+// WASD 俯仰与偏航，QE 滚转，左 Shift/Ctrl 调节油门，Z 满油门，X 关闭
+fn ship_control_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    time: Res<Time>,
+    mut controls: ResMut<ShipControls>,
+) {
+    let axis = |positive: KeyCode, negative: KeyCode| {
+        keyboard_input.pressed(positive) as i32 as f32 - keyboard_input.pressed(negative) as i32 as f32
+    };
+    let mut new_controls = *controls;
+    new_controls.rotation = Vec3::new(
+        axis(KeyCode::KeyS, KeyCode::KeyW),
+        axis(KeyCode::KeyA, KeyCode::KeyD),
+        axis(KeyCode::KeyQ, KeyCode::KeyE),
+    );
+    new_controls.throttle += axis(KeyCode::ShiftLeft, KeyCode::ControlLeft) * THROTTLE_RATE * time.delta_seconds();
+    if keyboard_input.just_pressed(KeyCode::KeyZ) {
+        new_controls.throttle = 1.0;
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyX) {
+        new_controls.throttle = 0.0;
+    }
+    new_controls.throttle = new_controls.throttle.clamp(0.0, 1.0);
+    // 只在输入变化时写入，回放录制据此记录操纵
+    if new_controls != *controls {
+        *controls = new_controls;
+    }
+}
+
+// 机头为模型的 +z 方向；松开姿态键时角速度归零
+pub fn apply_ship_controls(
+    mut ships: Query<(&Transform, &mut MotionComp, &mut GravitationComp, &mut PlayerShip)>,
+    controls: Res<ShipControls>,
+    time: Res<Time>,
+) {
+    for (transform, mut motion, mut gravitation, mut ship) in ships.iter_mut() {
+        motion.angular_velocity = transform.rotation * controls.rotation * ATTITUDE_RATE;
+        let thrust = ship.max_thrust * controls.throttle;
+        let acceleration = ship.burn(thrust, time.delta_seconds());
+        gravitation.mass = ship.mass();
+        motion.acceleration += transform.rotation * Vec3::Z * acceleration;
+    }
+}
+
+fn report_ship_collision(
+    mut collision_events: EventReader<CollisionDetectionEvent>,
+    ships: Query<(), With<PlayerShip>>,
+    names: Query<&Name>,
+    mut outcome: ResMut<RunOutcome>,
+) {
+    for event in collision_events.read() {
+        let other = if ships.contains(event.entity) {
+            event.other_entity
+        } else if ships.contains(event.other_entity) {
+            event.entity
+        } else {
+            continue;
+        };
+        let name = names.get(other).map_or_else(|_| format!("{:?}", other), |name| name.to_string());
+        outcome.reason = format!("The ship crashed into {}", name);
+    }
+}
+
+/// 在 (位置, 质量) 中找出对 position 处引力最大的天体，返回其序号；质量为零的项不参与
+pub fn dominant_attractor(position: Vec3, bodies: impl IntoIterator<Item = (Vec3, f32)>) -> Option<usize> {
+    bodies.into_iter()
+        .enumerate()
+        .filter(|(_, (_, mass))| *mass > 0.0)
+        .map(|(index, (body_position, mass))| (index, mass / body_position.distance_squared(position)))
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(index, _)| index)
+}
+
+/// 速度与位移共线时轨道面不确定，返回 None
+pub fn orbit_frame(offset: Vec3, velocity: Vec3) -> Option<OrbitFrame> {
+    let prograde = velocity.try_normalize()?;
+    let normal = offset.cross(velocity).try_normalize()?;
+    Some(OrbitFrame { prograde, normal, radial: prograde.cross(normal) })
+}
+
+// 导航球以机头为圆心，右为 -x、上为 +y；机头后方的标记画成空心
+fn navball(
+    mut contexts: EguiContexts,
+    ships: Query<(Entity, &Transform, &MotionComp, &PlayerShip)>,
+    bodies: Query<(Entity, &Transform, &MotionComp, &GravitationComp)>,
+    units: Res<UnitSystem>,
+    controls: Res<ShipControls>,
+) {
+    let Ok((ship_entity, ship_transform, ship_motion, ship)) = ships.get_single() else { return; };
+    let position = ship_transform.translation;
+    let attractors: Vec<(Vec3, Vec3, f32)> = bodies.iter()
+        .filter(|(entity, ..)| *entity != ship_entity)
+        .map(|(_, transform, motion, gravitation)| (transform.translation, motion.velocity, gravitation.mass))
+        .collect();
+    let attractor = dominant_attractor(position, attractors.iter().map(|(position, _, mass)| (*position, *mass)));
+    let (offset, velocity) = attractor.map_or((position, ship_motion.velocity), |index| {
+        (position - attractors[index].0, ship_motion.velocity - attractors[index].1)
+    });
+    let frame = orbit_frame(offset, velocity);
+    let to_local = ship_transform.rotation.inverse();
+    egui::Window::new("Navball")
+        .anchor(egui::Align2::CENTER_BOTTOM, [0.0, -10.0])
+        .resizable(false)
+        .collapsible(false)
+        .show(contexts.ctx_mut(), |ui| {
+            let size = egui::vec2(NAVBALL_RADIUS * 2.0 + 20.0, NAVBALL_RADIUS * 2.0 + 20.0);
+            let (response, painter) = ui.allocate_painter(size, egui::Sense::hover());
+            let center = response.rect.center();
+            painter.circle_filled(center, NAVBALL_RADIUS, egui::Color32::from_rgb(30, 40, 70));
+            painter.line_segment([center - egui::vec2(8.0, 0.0), center + egui::vec2(8.0, 0.0)], (2.0, egui::Color32::YELLOW));
+            painter.line_segment([center - egui::vec2(0.0, 8.0), center + egui::vec2(0.0, 8.0)], (2.0, egui::Color32::YELLOW));
+            if let Some(frame) = frame {
+                let markers = [
+                    (frame.prograde, "PRO", egui::Color32::from_rgb(230, 220, 60)),
+                    (-frame.prograde, "RETRO", egui::Color32::from_rgb(230, 220, 60)),
+                    (frame.normal, "NRM", egui::Color32::from_rgb(220, 80, 220)),
+                    (-frame.normal, "ANTI", egui::Color32::from_rgb(220, 80, 220)),
+                    (frame.radial, "RAD+", egui::Color32::from_rgb(80, 220, 230)),
+                    (-frame.radial, "RAD-", egui::Color32::from_rgb(80, 220, 230)),
+                ];
+                for (direction, label, color) in markers {
+                    let local = to_local * direction;
+                    let marker = center + egui::vec2(-local.x, -local.y) * NAVBALL_RADIUS;
+                    if local.z >= 0.0 {
+                        painter.circle_filled(marker, 5.0, color);
+                    } else {
+                        painter.circle_stroke(marker, 5.0, (1.5, color));
+                    }
+                    painter.text(marker + egui::vec2(7.0, 0.0), egui::Align2::LEFT_CENTER, label, egui::FontId::monospace(11.0), color);
+                }
+            }
+            ui.label(format!("throttle: {:.0}%", controls.throttle * 100.0));
+            ui.label(format!("fuel: {:.2} {}, mass: {:.2} {}", ship.fuel, units.mass_unit(), ship.mass(), units.mass_unit()));
+            ui.label(format!("speed: {:.3} {}, distance: {:.3} {}", velocity.length(), units.velocity_unit(), offset.length(), units.length_unit()));
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn circular_orbit_frame_is_orthonormal_with_radial_outward() {
+        let frame = orbit_frame(Vec3::X * 10.0, Vec3::Z * 3.0).unwrap();
+        assert!(frame.prograde.abs_diff_eq(Vec3::Z, 1e-6));
+        assert!(frame.radial.abs_diff_eq(Vec3::X, 1e-6));
+        assert!(frame.normal.dot(frame.prograde).abs() < 1e-6 && frame.normal.dot(frame.radial).abs() < 1e-6);
+        assert!(orbit_frame(Vec3::X, Vec3::X).is_none());
+    }
+}
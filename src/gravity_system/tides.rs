@@ -0,0 +1,104 @@
+use bevy::prelude::*;
+
+use super::{
+    collision_detection::CollisionDetection,
+    gravitation::GravitationComp,
+    motion::{BodyId, MotionComp},
+    units::UnitSystem,
+    GravityStatusUpdateSet,
+};
+
+/// 场景开启潮汐锁定时插入
+#[derive(Resource, Debug)]
+pub struct TidalTorque {
+    pub strength: f32,
+}
+
+pub struct TidesPlugin;
+impl Plugin for TidesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedUpdate,
+            tidal_torque.run_if(resource_exists::<TidalTorque>).in_set(GravityStatusUpdateSet::VelocityUpdate));
+    }
+}
+
+// 潮汐力矩 ∝ G * M² * R⁵ / r⁶，转动惯量 ∝ m * R²，自转角速度以 strength * G * M² * R³ / (m * r⁶)
+// 的角加速度趋向公转角速度，其中 M 为引力占主导的天体，不会越过同步点
+fn tidal_torque(
+    mut bodies: Query<(Entity, &BodyId, &Transform, &GravitationComp, &CollisionDetection, &mut MotionComp)>,
+    tidal_torque: Res<TidalTorque>,
+    units: Res<UnitSystem>,
+    time: Res<Time>,
+) {
+    // 按 BodyId 排序，引力相同时选出的主天体不随实体顺序变化
+    let mut attractors: Vec<(BodyId, Entity, Vec3, Vec3, f32)> = bodies.iter()
+        .map(|(entity, id, transform, gravitation, _, motion)| (*id, entity, transform.translation, motion.velocity, gravitation.mass))
+        .collect();
+    attractors.sort_by_key(|(id, ..)| *id);
+    let attractors: Vec<(Entity, Vec3, Vec3, f32)> = attractors.into_iter()
+        .map(|(_, entity, position, velocity, mass)| (entity, position, velocity, mass))
+        .collect();
+    let gravitational_constant = units.gravitational_constant();
+    for (entity, _, transform, gravitation, collision, mut motion) in bodies.iter_mut() {
+        if gravitation.mass <= 0.0 { continue; }
+        let dominant = attractors.iter()
+            .filter(|(other, ..)| *other != entity)
+            .max_by(|(_, a_position, _, a_mass), (_, b_position, _, b_mass)| {
+                let a_pull = a_mass / a_position.distance_squared(transform.translation);
+                let b_pull = b_mass / b_position.distance_squared(transform.translation);
+                a_pull.total_cmp(&b_pull)
+            });
+        let Some((_, attractor_position, attractor_velocity, attractor_mass)) = dominant else { continue; };
+        let offset = transform.translation - *attractor_position;
+        let orbital_angular_velocity = offset.cross(motion.velocity - *attractor_velocity) / offset.length_squared();
+        let angular_acceleration = tidal_spin_rate(
+            tidal_torque.strength * gravitational_constant, *attractor_mass, gravitation.mass, collision.radius, offset.length(),
+        );
+        motion.angular_velocity = spin_toward(motion.angular_velocity, orbital_angular_velocity, angular_acceleration, time.delta_seconds());
+    }
+}
+
+/// 潮汐锁定的角加速度，`strength` 已乘以引力常数。国际单位制下 M² 与 r⁶ 会超出 f32 范围，按 f64 计算
+fn tidal_spin_rate(strength: f32, attractor_mass: f32, mass: f32, radius: f32, distance: f32) -> f32 {
+    (strength as f64 * (attractor_mass as f64).powi(2) * (radius as f64).powi(3)
+        / (mass as f64 * (distance as f64).powi(6))) as f32
+}
+
+/// 自转角速度以给定角加速度趋向目标，一步之内最多恰好到达目标
+fn spin_toward(angular_velocity: Vec3, target: Vec3, angular_acceleration: f32, dt: f32) -> Vec3 {
+    let difference = angular_velocity - target;
+    let change = (angular_acceleration * dt).min(difference.length());
+    angular_velocity - difference.normalize_or_zero() * change
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn spin_down_rate_scales_with_radius_cubed() {
+        let small = tidal_spin_rate(1.0, 100.0, 1.0, 1.0, 10.0);
+        assert!((small - 1.0e-2).abs() < 1e-8);
+        // 质量相同时半径大一倍，锁定快八倍；距离远一倍，慢 64 倍
+        assert!((tidal_spin_rate(1.0, 100.0, 1.0, 2.0, 10.0) / small - 8.0).abs() < 1e-4);
+        assert!((small / tidal_spin_rate(1.0, 100.0, 1.0, 1.0, 20.0) - 64.0).abs() < 1e-3);
+        // 太阳质量与地球距离在 f32 下也不溢出
+        assert!(tidal_spin_rate(6.674e-11, 1.989e30, 7.35e22, 1.737e6, 1.496e11).is_finite());
+    }
+    #[test]
+    fn spin_converges_without_overshoot() {
+        let orbital = Vec3::new(0.0, 0.5, 0.0);
+        let mut spin = Vec3::new(0.0, 3.0, 0.0);
+        let mut last_gap = f32::INFINITY;
+        // 单步的角速度变化远大于差值
+        for _ in 0..5 {
+            spin = spin_toward(spin, orbital, 2.0, 10.0);
+            assert!(spin.y >= orbital.y && spin.y > 0.0);
+            let gap = spin.distance(orbital);
+            assert!(gap <= last_gap);
+            last_gap = gap;
+        }
+        assert!(spin.distance(orbital) < 1e-6);
+        let slow = spin_toward(Vec3::new(0.0, 3.0, 0.0), orbital, 0.1, 1.0);
+        assert!((slow.y - 2.9).abs() < 1e-6);
+    }
+}
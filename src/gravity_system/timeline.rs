@@ -0,0 +1,175 @@
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+
+use crate::asset_loader::{ModelCatalog, SceneAssets};
+use crate::loading_state::AppState;
+
+use super::{
+    camera::CameraFocus,
+    gravitation::GravitationComp,
+    motion::{MotionComp, SimulationTime},
+    planet::{planet_source, spawn_planet},
+    running_state::{ResetEvent, RunningState},
+    scenario::{ActionJson, ConditionJson, Scenario},
+    units::UnitSystem,
+    GravityStatusUpdateSet,
+};
+
+/// 场景时间线中到期的一项，在同一固定步内执行
+#[derive(Event, Debug, Clone)]
+pub struct TimelineActionEvent {
+    pub index: usize,
+    pub action: ActionJson,
+}
+
+/// 各项是否已触发，回退时随快照恢复
+#[derive(Resource, Debug, Default, Clone)]
+pub struct TimelineState {
+    fired: Vec<bool>,
+    /// 出现过的天体，用于判断 removed 条件
+    seen: HashSet<String>,
+}
+
+/// 暂停动作附带的说明，继续运行后清空
+#[derive(Resource, Debug, Default)]
+pub struct TimelineMessage(pub String);
+
+type TimelineBodyQuery<'a> = (Entity, &'a Name, &'a Transform, &'a mut MotionComp, Option<&'a mut GravitationComp>);
+
+pub struct TimelinePlugin;
+impl Plugin for TimelinePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TimelineState>();
+        app.init_resource::<TimelineMessage>();
+        app.add_event::<TimelineActionEvent>();
+        app.add_systems(OnEnter(AppState::Running), reset_timeline);
+        app.add_systems(PostUpdate, reset_timeline.run_if(on_event::<ResetEvent>()));
+        // 按固定步触发，回放时无需另行记录
+        app.add_systems(FixedUpdate, (trigger_timeline, apply_timeline_actions).chain()
+            .in_set(GravityStatusUpdateSet::Analysis)
+            .run_if(has_timeline));
+        app.add_systems(OnEnter(RunningState::Paused), spawn_timeline_message);
+        app.add_systems(OnExit(RunningState::Paused), |mut message: ResMut<TimelineMessage>| message.0.clear());
+    }
+}
+
+pub fn has_timeline(scenario: Res<Scenario>) -> bool {
+    !scenario.timeline.is_empty()
+}
+
+fn reset_timeline(mut state: ResMut<TimelineState>, scenario: Res<Scenario>) {
+    *state = TimelineState {
+        fired: vec![false; scenario.timeline.len()],
+        seen: HashSet::new(),
+    };
+}
+
+fn trigger_timeline(
+    scenario: Res<Scenario>,
+    mut state: ResMut<TimelineState>,
+    simulation_time: Res<SimulationTime>,
+    bodies: Query<(&Name, &Transform, &MotionComp)>,
+    mut action_event_writer: EventWriter<TimelineActionEvent>,
+) {
+    let find = |name: &str| bodies.iter().find(|(body, ..)| body.as_str() == name);
+    let distance = |pair: &[String; 2]| match (find(&pair[0]), find(&pair[1])) {
+        (Some((_, a, _)), Some((_, b, _))) => Some(a.translation.distance(b.translation)),
+        _ => None,
+    };
+    state.seen.extend(bodies.iter().map(|(name, ..)| name.to_string()));
+    for (index, event) in scenario.timeline.iter().enumerate() {
+        if state.fired.get(index).copied().unwrap_or(true) { continue; }
+        if event.at.is_some_and(|at| simulation_time.elapsed < at) { continue; }
+        let satisfied = match &event.when {
+            None => true,
+            Some(ConditionJson::CloserThan { bodies, distance: limit }) => distance(bodies).is_some_and(|distance| distance < *limit),
+            Some(ConditionJson::FartherThan { bodies, distance: limit }) => distance(bodies).is_some_and(|distance| distance > *limit),
+            Some(ConditionJson::SpeedAbove { body, speed }) => find(body).is_some_and(|(.., motion)| motion.velocity.length() > *speed),
+            Some(ConditionJson::Removed { body }) => state.seen.contains(body) && find(body).is_none(),
+        };
+        if !satisfied { continue; }
+        state.fired[index] = true;
+        action_event_writer.send(TimelineActionEvent { index, action: event.action.clone() });
+    }
+}
+
+fn apply_timeline_actions(
+    mut commands: Commands,
+    mut action_events: EventReader<TimelineActionEvent>,
+    mut bodies: Query<TimelineBodyQuery>,
+    scene_assets: Res<SceneAssets>,
+    model_catalog: Res<ModelCatalog>,
+    units: Res<UnitSystem>,
+    mut next_state: ResMut<NextState<RunningState>>,
+) {
+    for event in action_events.read() {
+        match &event.action {
+            ActionJson::DeltaV { body: name, delta_v } => {
+                if let Some((.., mut motion, _)) = bodies.iter_mut().find(|(_, body, ..)| body.as_str() == name) {
+                    motion.velocity += Vec3::from(*delta_v);
+                }
+            }
+            ActionJson::SetMass { body: name, mass } => {
+                if let Some((.., Some(mut gravitation))) = bodies.iter_mut().find(|(_, body, ..)| body.as_str() == name) {
+                    gravitation.mass = *mass;
+                }
+            }
+            ActionJson::Spawn { body: planet, star } => {
+                let mut planet = planet.clone();
+                let parent = planet.parent.as_deref().map(|name| {
+                    bodies.iter().find(|(_, body, ..)| body.as_str() == name).map(|(_, _, transform, motion, gravitation)| {
+                        (transform.translation, motion.velocity, gravitation.map_or(0.0, |gravitation| gravitation.mass))
+                    })
+                });
+                let parent = match parent {
+                    Some(None) => {
+                        warn!("timeline[{}]: parent of {:?} no longer exists", event.index, planet.name);
+                        continue;
+                    }
+                    Some(Some(parent)) => Some(parent),
+                    None => None,
+                };
+                let (parent_position, parent_velocity, parent_mass) = parent.unwrap_or((Vec3::ZERO, Vec3::ZERO, 0.0));
+                let Some((position, velocity)) = planet.relative_state(parent_mass, units.gravitational_constant()) else { continue; };
+                planet.position = (parent_position + position).into();
+                planet.velocity = (parent_velocity + velocity).into();
+                planet.parent = None;
+                planet.orbit = None;
+                let key = planet.name.clone().unwrap_or_default();
+                spawn_planet(&mut commands, planet_source(planet, &key, *star, &scene_assets, &model_catalog));
+            }
+            ActionJson::Pause { message } => {
+                commands.insert_resource(TimelineMessage(message.clone()));
+                next_state.set(RunningState::Paused);
+            }
+            ActionJson::Focus { body: name } => {
+                if let Some((entity, ..)) = bodies.iter().find(|(_, body, ..)| body.as_str() == name) {
+                    commands.insert_resource(CameraFocus(Some(entity)));
+                }
+            }
+        }
+    }
+}
+
+fn spawn_timeline_message(mut commands: Commands, message: Res<TimelineMessage>) {
+    if message.0.is_empty() { return; }
+    commands.spawn((
+        TextBundle::from_section(
+            message.0.clone(),
+            TextStyle {
+                font_size: 28.,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_text_justify(JustifyText::Center)
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Percent(65.),
+            left: Val::Percent(10.),
+            width: Val::Percent(80.),
+            ..default()
+        }),
+        StateScoped(RunningState::Paused),
+    ));
+}
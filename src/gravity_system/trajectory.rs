@@ -0,0 +1,258 @@
+use std::fs::{self, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::input::common_conditions::input_just_pressed;
+use bevy::prelude::*;
+
+use super::{
+    motion::{BodyId, MotionComp, SimulationTime},
+    planet::PlanetSource,
+    rewind::RewindEvent,
+    running_state::{ResetEvent, RunningState},
+    scenario::Scenario,
+    units::UnitSystem,
+    GravityStatusUpdateSet,
+};
+
+const RUN_ROOT: &str = "output/runs";
+const RECORDER_CAPACITY: usize = 4096;
+
+#[derive(Debug, Clone, Copy)]
+struct TrajectorySample {
+    tick: u64,
+    time: f64,
+    position: Vec3,
+    velocity: Vec3,
+    acceleration: Vec3,
+}
+
+/// 每隔 `interval` 个固定步记录一次位置、速度与加速度。
+/// 缓冲区在创建时一次分配，写出后清空复用；写出前已满的采样会被丢弃并计数
+#[derive(Component, Debug)]
+pub struct TrajectoryRecorder {
+    pub interval: u64,
+    samples: Vec<TrajectorySample>,
+    dropped: u64,
+    /// 首次采样时所在的运行目录
+    run: Option<Arc<PathBuf>>,
+}
+
+impl TrajectoryRecorder {
+    pub fn new(interval: u64) -> Self {
+        Self {
+            interval: interval.max(1),
+            samples: Vec::with_capacity(RECORDER_CAPACITY),
+            dropped: 0,
+            run: None,
+        }
+    }
+}
+
+/// 本次运行的输出目录，每次重置或回退后换一个新目录
+#[derive(Resource, Debug)]
+pub struct TrajectoryRun {
+    pub dir: Arc<PathBuf>,
+    index: u32,
+}
+
+impl Default for TrajectoryRun {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl TrajectoryRun {
+    fn new(index: u32) -> Self {
+        let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs());
+        Self {
+            dir: Arc::new(Path::new(RUN_ROOT).join(format!("{}-{}", seconds, index))),
+            index,
+        }
+    }
+}
+
+type NewBodyFilter = (Added<PlanetSource>, Without<TrajectoryRecorder>);
+type FlushQuery<'a> = (&'a mut TrajectoryRecorder, Option<&'a BodyId>, Option<&'a Name>);
+
+pub struct TrajectoryPlugin;
+impl Plugin for TrajectoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TrajectoryRun>();
+        app.add_systems(Update, attach_scenario_recorders.run_if(resource_exists::<Scenario>));
+        app.add_systems(FixedUpdate, record_trajectories.in_set(GravityStatusUpdateSet::Record));
+        app.add_systems(Update, flush_full_recorders);
+        app.add_systems(Update, flush_all_recorders.run_if(input_just_pressed(KeyCode::KeyK)));
+        app.add_systems(OnEnter(RunningState::End), flush_all_recorders);
+        app.add_systems(Last, flush_all_recorders.run_if(on_event::<AppExit>()));
+        app.add_systems(PostUpdate, next_run.run_if(on_event::<ResetEvent>()));
+        app.add_systems(PostUpdate, split_run_on_rewind.run_if(on_event::<RewindEvent>()));
+        // 天体被重置、碰撞或逃逸移除时写出其剩余的采样
+        app.observe(flush_removed_recorder);
+    }
+}
+
+fn attach_scenario_recorders(
+    mut commands: Commands,
+    bodies: Query<(Entity, Option<&Name>), NewBodyFilter>,
+    scenario: Res<Scenario>,
+) {
+    let Some(trajectories) = &scenario.trajectories else { return; };
+    for (entity, name) in bodies.iter() {
+        let selected = trajectories.bodies.is_empty()
+            || name.is_some_and(|name| trajectories.bodies.iter().any(|body| body == name.as_str()));
+        if selected {
+            commands.entity(entity).insert(TrajectoryRecorder::new(trajectories.interval));
+        }
+    }
+}
+
+fn record_trajectories(
+    mut recorders: Query<(&Transform, &MotionComp, &mut TrajectoryRecorder)>,
+    simulation_time: Res<SimulationTime>,
+    run: Res<TrajectoryRun>,
+) {
+    for (transform, motion, mut recorder) in recorders.iter_mut() {
+        if !simulation_time.tick.is_multiple_of(recorder.interval) { continue; }
+        if recorder.samples.len() == recorder.samples.capacity() {
+            recorder.dropped += 1;
+            continue;
+        }
+        if recorder.run.is_none() {
+            recorder.run = Some(run.dir.clone());
+        }
+        recorder.samples.push(TrajectorySample {
+            tick: simulation_time.tick,
+            time: simulation_time.elapsed,
+            position: transform.translation,
+            velocity: motion.velocity,
+            acceleration: motion.acceleration,
+        });
+    }
+}
+
+// 过半即写出，固定步在两帧之间不至于把缓冲区填满
+fn flush_full_recorders(mut recorders: Query<FlushQuery>, units: Res<UnitSystem>) {
+    for (mut recorder, id, name) in recorders.iter_mut() {
+        if recorder.samples.len() * 2 >= recorder.samples.capacity() {
+            flush_recorder(&mut recorder, id, name, &units);
+        }
+    }
+}
+
+fn flush_all_recorders(mut recorders: Query<FlushQuery>, units: Res<UnitSystem>) {
+    for (mut recorder, id, name) in recorders.iter_mut() {
+        flush_recorder(&mut recorder, id, name, &units);
+    }
+}
+
+fn flush_removed_recorder(trigger: Trigger<OnRemove, TrajectoryRecorder>, mut recorders: Query<FlushQuery>, units: Res<UnitSystem>) {
+    let Ok((mut recorder, id, name)) = recorders.get_mut(trigger.entity()) else { return; };
+    flush_recorder(&mut recorder, id, name, &units);
+}
+
+fn next_run(mut run: ResMut<TrajectoryRun>) {
+    *run = TrajectoryRun::new(run.index + 1);
+}
+
+// 回退前的采样写入原目录，之后的采样写入新目录，避免同一文件中时间倒流
+fn split_run_on_rewind(mut recorders: Query<FlushQuery>, units: Res<UnitSystem>, mut run: ResMut<TrajectoryRun>) {
+    for (mut recorder, id, name) in recorders.iter_mut() {
+        flush_recorder(&mut recorder, id, name, &units);
+        recorder.run = None;
+    }
+    *run = TrajectoryRun::new(run.index + 1);
+}
+
+fn flush_recorder(recorder: &mut TrajectoryRecorder, id: Option<&BodyId>, name: Option<&Name>, units: &UnitSystem) {
+    if recorder.dropped > 0 {
+        warn!("trajectory recorder of {:?} dropped {} samples", name, recorder.dropped);
+        recorder.dropped = 0;
+    }
+    let Some(run) = recorder.run.clone() else { return; };
+    if recorder.samples.is_empty() { return; }
+    let stem: String = name.map_or("body", |name| name.as_str()).chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let path = run.join(format!("{}-{}.csv", stem, id.map_or(0, |id| id.0)));
+    match write_samples(&path, &recorder.samples, units) {
+        Ok(()) => info!("{} trajectory samples written to {}", recorder.samples.len(), path.display()),
+        Err(err) => error!("failed to write trajectory to {}: {}", path.display(), err),
+    }
+    recorder.samples.clear();
+}
+
+// 追加写入，同一天体多次写出时只在文件开头写一次表头
+fn write_samples(path: &Path, samples: &[TrajectorySample], units: &UnitSystem) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let empty = file.metadata()?.len() == 0;
+    let mut writer = BufWriter::new(file);
+    if empty {
+        let (length, velocity, acceleration) = (units.length_unit(), units.velocity_unit(), units.acceleration_unit());
+        writeln!(
+            writer,
+            "tick,time ({}),x ({}),y,z,vx ({}),vy,vz,ax ({}),ay,az",
+            units.time_unit(), length, velocity, acceleration,
+        )?;
+    }
+    for sample in samples.iter() {
+        let (p, v, a) = (sample.position, sample.velocity, sample.acceleration);
+        writeln!(
+            writer, "{},{},{},{},{},{},{},{},{},{},{}",
+            sample.tick, sample.time, p.x, p.y, p.z, v.x, v.y, v.z, a.x, a.y, a.z,
+        )?;
+    }
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+    use super::*;
+    #[test]
+    fn flushing_reuses_the_buffer_and_appends() {
+        let dir = std::env::temp_dir().join(format!("trajectory-test-{}", std::process::id()));
+        let mut recorder = TrajectoryRecorder::new(1);
+        recorder.run = Some(Arc::new(dir.clone()));
+        let capacity = recorder.samples.capacity();
+        for round in 0..2 {
+            for tick in 0..capacity as u64 {
+                recorder.samples.push(TrajectorySample {
+                    tick: round * capacity as u64 + tick,
+                    time: tick as f64,
+                    position: Vec3::X,
+                    velocity: Vec3::Y,
+                    acceleration: Vec3::Z,
+                });
+            }
+            flush_recorder(&mut recorder, Some(&BodyId(3)), Some(&Name::new("Blue I")), &UnitSystem::default());
+            assert_eq!(recorder.samples.capacity(), capacity);
+        }
+        let csv = fs::read_to_string(dir.join("Blue_I-3.csv")).unwrap();
+        assert_eq!(csv.lines().count(), 1 + 2 * capacity);
+        assert_eq!(csv.lines().filter(|line| line.starts_with("tick")).count(), 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+    #[test]
+    fn rewind_flushes_and_starts_a_new_run() {
+        let dir = std::env::temp_dir().join(format!("trajectory-rewind-test-{}", std::process::id()));
+        let mut world = World::new();
+        world.init_resource::<UnitSystem>();
+        world.insert_resource(TrajectoryRun { dir: Arc::new(dir.clone()), index: 0 });
+        let mut recorder = TrajectoryRecorder::new(1);
+        recorder.run = Some(Arc::new(dir.clone()));
+        recorder.samples.push(TrajectorySample { tick: 64, time: 1.0, position: Vec3::X, velocity: Vec3::Y, acceleration: Vec3::Z });
+        let entity = world.spawn((recorder, BodyId(1), Name::new("Red"))).id();
+        world.run_system_once(split_run_on_rewind);
+        let recorder = world.get::<TrajectoryRecorder>(entity).unwrap();
+        assert!(recorder.samples.is_empty() && recorder.run.is_none());
+        assert_eq!(world.resource::<TrajectoryRun>().index, 1);
+        assert_eq!(fs::read_to_string(dir.join("Red-1.csv")).unwrap().lines().count(), 2);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,114 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+const SI_GRAVITATIONAL_CONSTANT: f64 = 6.6743e-11;
+const ASTRONOMICAL_UNIT: f64 = 1.495_978_707e11;
+const SOLAR_MASS: f64 = 1.988_41e30;
+const DAY: f64 = 86_400.0;
+const SI_SPEED_OF_LIGHT: f64 = 299_792_458.0;
+
+/// 场景使用的单位制，决定引力常数以及界面与导出文件中的单位
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UnitSystem {
+    /// 米、千克、秒
+    #[default]
+    Si,
+    /// 天文单位、太阳质量、天
+    Astronomical,
+    /// G = 1，长度与质量同天文单位制，时间单位随之确定（约 58.13 天）
+    NBody,
+}
+
+impl UnitSystem {
+    pub fn from_arg(value: &str) -> Option<Self> {
+        match value {
+            "si" => Some(Self::Si),
+            "au" | "astronomical" => Some(Self::Astronomical),
+            "nbody" | "n_body" => Some(Self::NBody),
+            _ => None,
+        }
+    }
+
+    pub fn gravitational_constant(self) -> f32 {
+        match self {
+            Self::NBody => 1.0,
+            _ => (SI_GRAVITATIONAL_CONSTANT * self.mass_in_kilograms() * self.time_in_seconds().powi(2)
+                / self.length_in_meters().powi(3)) as f32,
+        }
+    }
+
+    pub fn speed_of_light(self) -> f32 {
+        (SI_SPEED_OF_LIGHT * self.time_in_seconds() / self.length_in_meters()) as f32
+    }
+
+    pub fn length_in_meters(self) -> f64 {
+        match self {
+            Self::Si => 1.0,
+            Self::Astronomical | Self::NBody => ASTRONOMICAL_UNIT,
+        }
+    }
+
+    pub fn mass_in_kilograms(self) -> f64 {
+        match self {
+            Self::Si => 1.0,
+            Self::Astronomical | Self::NBody => SOLAR_MASS,
+        }
+    }
+
+    pub fn in_solar_masses(self, mass: f32) -> f64 {
+        mass as f64 * self.mass_in_kilograms() / SOLAR_MASS
+    }
+
+    pub fn time_in_seconds(self) -> f64 {
+        match self {
+            Self::Si => 1.0,
+            Self::Astronomical => DAY,
+            Self::NBody => (ASTRONOMICAL_UNIT.powi(3) / (SI_GRAVITATIONAL_CONSTANT * SOLAR_MASS)).sqrt(),
+        }
+    }
+
+    pub fn length_unit(self) -> &'static str {
+        match self {
+            Self::Si => "m",
+            Self::Astronomical => "AU",
+            Self::NBody => "L",
+        }
+    }
+
+    pub fn mass_unit(self) -> &'static str {
+        match self {
+            Self::Si => "kg",
+            Self::Astronomical => "M☉",
+            Self::NBody => "M",
+        }
+    }
+
+    pub fn time_unit(self) -> &'static str {
+        match self {
+            Self::Si => "s",
+            Self::Astronomical => "d",
+            Self::NBody => "T",
+        }
+    }
+
+    pub fn velocity_unit(self) -> String {
+        format!("{}/{}", self.length_unit(), self.time_unit())
+    }
+
+    pub fn acceleration_unit(self) -> String {
+        format!("{}/{}²", self.length_unit(), self.time_unit())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn gravitational_constant_matches_unit_system() {
+        assert!((UnitSystem::Si.gravitational_constant() - 6.6743e-11).abs() < 1e-15);
+        // 高斯引力常数 k 的平方
+        assert!((UnitSystem::Astronomical.gravitational_constant() as f64 - 0.01720209895_f64.powi(2)).abs() < 1e-7);
+        assert_eq!(UnitSystem::NBody.gravitational_constant(), 1.0);
+    }
+}
@@ -0,0 +1,136 @@
+use bevy::{
+    asset::{LoadState, RecursiveDependencyLoadState},
+    gltf::Gltf,
+    prelude::*,
+};
+
+/// 应用级状态：资源全部加载完毕后才进入当前示例的运行状态
+#[derive(States, Clone, Copy, Default, Eq, PartialEq, Hash, Debug)]
+pub enum AppState {
+    #[default]
+    Loading,
+    Running,
+}
+
+#[derive(Debug, Clone)]
+pub struct LoadFailure {
+    pub path: String,
+    pub reason: String,
+}
+
+/// 加载界面等待的资源句柄以及加载失败的资源与配置文件
+#[derive(Resource, Debug, Default)]
+pub struct LoadingAssets {
+    pending: Vec<(String, UntypedHandle)>,
+    total: usize,
+    pub failures: Vec<LoadFailure>,
+}
+impl LoadingAssets {
+    pub fn track(&mut self, path: impl Into<String>, handle: impl Into<UntypedHandle>) {
+        self.pending.push((path.into(), handle.into()));
+        self.total += 1;
+    }
+
+    pub fn fail(&mut self, path: impl Into<String>, reason: impl ToString) {
+        record_failure(&mut self.failures, path.into(), reason.to_string());
+    }
+
+    /// 加载 glTF 文件中的第一个场景。等待的是文件本身，
+    /// 因为文件加载失败时其中带标签的子资源不会进入失败状态
+    pub fn load_scene(&mut self, asset_server: &AssetServer, path: &str) -> Handle<Scene> {
+        self.track(path, asset_server.load::<Gltf>(path.to_string()));
+        asset_server.load(GltfAssetLabel::Scene(0).from_asset(path.to_string()))
+    }
+
+    pub fn load_image(&mut self, asset_server: &AssetServer, path: &str) -> Handle<Image> {
+        let image = asset_server.load::<Image>(path.to_string());
+        self.track(path, image.clone());
+        image
+    }
+}
+
+fn record_failure(failures: &mut Vec<LoadFailure>, path: String, reason: String) {
+    error!("failed to load {}: {}", path, reason);
+    failures.push(LoadFailure { path, reason });
+}
+
+#[derive(Component)]
+struct LoadingText;
+
+pub struct LoadingStatePlugin;
+impl Plugin for LoadingStatePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<AppState>().enable_state_scoped_entities::<AppState>();
+        app.init_resource::<LoadingAssets>();
+        app.add_systems(OnEnter(AppState::Loading), spawn_loading_screen);
+        app.add_systems(Update, (check_loading_progress, update_loading_screen).chain().run_if(in_state(AppState::Loading)));
+    }
+}
+
+fn spawn_loading_screen(mut commands: Commands) {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            background_color: Color::srgb(0.05, 0.0, 0.08).into(),
+            z_index: ZIndex::Global(100),
+            ..default()
+        },
+        StateScoped(AppState::Loading),
+    )).with_children(|parent| {
+        parent.spawn((
+            TextBundle::from_section(
+                "Loading...",
+                TextStyle {
+                    font_size: 24.,
+                    color: Color::WHITE,
+                    ..default()
+                }
+            ),
+            LoadingText,
+        ));
+    });
+}
+
+// 全部句柄都已加载或失败后：没有失败则直接进入运行状态，否则停在加载界面等待回车确认
+fn check_loading_progress(
+    mut loading: ResMut<LoadingAssets>,
+    asset_server: Res<AssetServer>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let LoadingAssets { pending, failures, .. } = &mut *loading;
+    pending.retain(|(path, handle)| match asset_server.get_load_states(handle.id()) {
+        Some((LoadState::Failed(err), _, _)) => {
+            record_failure(failures, path.clone(), err.to_string());
+            false
+        }
+        Some((_, _, RecursiveDependencyLoadState::Failed)) => {
+            record_failure(failures, path.clone(), "a dependency failed to load".to_string());
+            false
+        }
+        Some((_, _, RecursiveDependencyLoadState::Loaded)) => false,
+        _ => true,
+    });
+    if !pending.is_empty() { return; }
+    if failures.is_empty() || keyboard_input.just_pressed(KeyCode::Enter) {
+        next_state.set(AppState::Running);
+    }
+}
+
+fn update_loading_screen(mut query: Query<&mut Text, With<LoadingText>>, loading: Res<LoadingAssets>) {
+    let Ok(mut text) = query.get_single_mut() else { return; };
+    let mut value = format!("Loading... {}/{}", loading.total - loading.pending.len(), loading.total);
+    for failure in loading.failures.iter() {
+        value += &format!("\nfailed to load {}: {}", failure.path, failure.reason);
+    }
+    if loading.pending.is_empty() && !loading.failures.is_empty() {
+        value += "\n\nPress Enter to continue";
+    }
+    text.sections[0].value = value;
+}
@@ -1,10 +1,12 @@
 use bevy::prelude::*;
 
 use asset_loader::AssetLoaderPlugin;
+use loading_state::LoadingStatePlugin;
 
 
 mod spaceship;
 mod asset_loader;
+mod loading_state;
 mod gravity_system;
 mod wave_function_collapse;
 fn main() {
@@ -17,6 +19,7 @@ fn main() {
             ..default()
         })
         // .add_plugins(bevy_inspector_egui::quick::WorldInspectorPlugin::default())
+        .add_plugins(LoadingStatePlugin)
         .add_plugins(AssetLoaderPlugin)
         // .add_plugins(gravity_system::GravitySystemPlugin)
         // .add_plugins(spaceship::SpaceshipSystemPlugin)
@@ -3,7 +3,8 @@ use bevy::prelude::*;
 use rand::Rng;
 
 use crate::spaceship::movement::{Acceleration, MovingObjectBundle, Velocity};
-use crate::asset_loader::SceneAssets;
+use crate::asset_loader::{FitToRadius, SceneAssets};
+use crate::loading_state::AppState;
 
 use super::collision_detection::Collider;
 const VELOCITY_SCALAR: f32 = 5.0;
@@ -26,7 +27,7 @@ impl Plugin for AsteroidsPlugin {
         app.insert_resource(SpawnTimer {
             timer: Timer::from_seconds(SPAWN_TIME_SECONDS, TimerMode::Repeating),
         })
-        .add_systems(Update, (spawn_asteroids, rotate_asteroid_z, handle_asteroid_collisions));
+        .add_systems(Update, (spawn_asteroids, rotate_asteroid_z, handle_asteroid_collisions).run_if(in_state(AppState::Running)));
     }
 }
 
@@ -61,6 +62,7 @@ fn spawn_asteroids(
             },
             collider: Collider::new(2.5)
         },
+        FitToRadius(2.5),
         Asteroid,
     ));
 }
@@ -1,6 +1,7 @@
 use bevy::prelude::*;
 use crate::spaceship::movement::{ Acceleration, MovingObjectBundle, Velocity };
-use crate::asset_loader::SceneAssets;
+use crate::asset_loader::{FitToRadius, SceneAssets};
+use crate::loading_state::AppState;
 
 use super::collision_detection::Collider;
 const STARTING_TRANSLATION: Vec3 = Vec3::new(0.0, 0.0, -20.0);
@@ -20,9 +21,8 @@ pub struct SpaceshipMissile;
 pub struct SpaceshipPlugin;
 impl Plugin for SpaceshipPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(PostStartup, spawn_spaceship)
-        .add_systems(Update, spaceship_movement_controls).
-        add_systems(Update, spaceship_weapon_controls);
+        app.add_systems(OnEnter(AppState::Running), spawn_spaceship)
+        .add_systems(Update, (spaceship_movement_controls, spaceship_weapon_controls).run_if(in_state(AppState::Running)));
     }
 }
 
@@ -38,6 +38,7 @@ fn spawn_spaceship(mut commands: Commands, scene_assets: Res<SceneAssets>) {
             },
             collider: Collider::new(5.0)
         },
+        FitToRadius(5.0),
         Spaceship,
     ));
 }
@@ -89,15 +90,12 @@ fn spaceship_weapon_controls(
                 acceleration: Acceleration::new(Vec3::ZERO),
                 model: SceneBundle {
                     scene: scene_assets.missile.clone(),
-                    transform: Transform {
-                        translation: transform.translation + -transform.forward() * MISSILE_FORWARD_SPAWN_SCALAR,
-                        scale: Vec3::splat(0.1),
-                        ..default()
-                    },
+                    transform: Transform::from_translation(transform.translation + -transform.forward() * MISSILE_FORWARD_SPAWN_SCALAR),
                     ..default()
                 },
                 collider: Collider::new(1.0)
             },
+            FitToRadius(1.0),
             SpaceshipMissile
         ));
     }
@@ -26,7 +26,7 @@ pub struct BesideImpossible {
 #[derive(Debug, Deserialize, Clone)]
 pub struct ModelConfigOptions {
     beside_impossible: BesideImpossible,
-    asset_model: String
+    pub asset_model: String
 }
 
 pub type ConfigMap = HashMap<u32, ModelConfigOptions>;
@@ -1,24 +1,39 @@
 use std::fs;
 use bevy::prelude::*;
-use bevy::utils::HashSet;
+use bevy::utils::{HashMap, HashSet};
 use rand::seq::IteratorRandom;
 use rand::Rng;
 use core::{WaveConfig, WaveGrid, ConfigMap, GridPosition};
 
+use crate::loading_state::{AppState, LoadingAssets};
+
 mod camera;
 mod core;
 
 static GRID_WIDTH: u32 = 50;
 static GRID_HEIGHT: u32 = 50;
+static CONFIG_PATH: &str = "assets/json/wave_function_collapse_map.json";
+
+/// 预先加载的瓦片图片，按配置中的路径索引
+#[derive(Resource, Debug, Default)]
+struct TileImages(HashMap<String, Handle<Image>>);
 
 pub struct WaveFunctionCollapsePlugin;
 impl Plugin for WaveFunctionCollapsePlugin {
     fn build(&self, app: &mut App) {
-        let (wave_config, wave_grid) = get_init_resource();
+        app.add_plugins(camera::CameraPlugin);
+        let (wave_config, wave_grid) = match get_init_resource() {
+            Ok(resource) => resource,
+            Err(err) => {
+                app.world_mut().get_resource_or_insert_with(LoadingAssets::default).fail(CONFIG_PATH, err);
+                return;
+            }
+        };
         app.insert_resource(wave_config);
         app.insert_resource(wave_grid);
-        app.add_systems(FixedUpdate, (wave_function_collapse, update_grid_rendering));
-        app.add_plugins(camera::CameraPlugin);
+        app.init_resource::<TileImages>();
+        app.add_systems(Startup, load_tile_images);
+        app.add_systems(FixedUpdate, (wave_function_collapse, update_grid_rendering).run_if(in_state(AppState::Running)));
     }
 }
 
@@ -46,7 +61,7 @@ fn update_grid_rendering(
     config: Res<WaveConfig>,
     mut wave_grid: ResMut<WaveGrid>,
     mut commands: Commands,
-    asset_loader: Res<AssetServer>
+    tile_images: Res<TileImages>,
 ) {
     for grid_row in wave_grid.grid_value.get_mut_value().iter_mut() {
         for grid_item in grid_row.iter_mut() {
@@ -57,7 +72,7 @@ fn update_grid_rendering(
             let scale = 0.1;
             if let Some(_) = grid_item.get_value() {
                 commands.spawn(SpriteBundle {
-                    texture: asset_loader.load(asset_path_str),
+                    texture: tile_images.0.get(&asset_path_str).cloned().unwrap_or_default(),
                     transform: Transform {
                         translation: Vec3::new(position.x as f32 * 128. * scale, position.y as f32 * 128. * scale,0.0),
                         scale: Vec3::new(scale, scale, 0.),
@@ -71,8 +86,22 @@ fn update_grid_rendering(
     }
 }
 
-fn get_init_resource() -> (WaveConfig, WaveGrid) {
-    let (config, all_possible_value) = get_config();
+fn load_tile_images(
+    config: Res<WaveConfig>,
+    mut tile_images: ResMut<TileImages>,
+    mut loading: ResMut<LoadingAssets>,
+    asset_server: Res<AssetServer>,
+) {
+    for options in config.0.values() {
+        let path = options.asset_model.clone();
+        if tile_images.0.contains_key(&path) { continue; }
+        let image = loading.load_image(&asset_server, &path);
+        tile_images.0.insert(path, image);
+    }
+}
+
+fn get_init_resource() -> Result<(WaveConfig, WaveGrid), String> {
+    let (config, all_possible_value) = get_config()?;
     let random_value = all_possible_value.iter().choose(&mut rand::thread_rng()).unwrap().clone();
     let random_x = rand::thread_rng().gen_range(0..GRID_WIDTH);
     let random_y = rand::thread_rng().gen_range(0..GRID_HEIGHT);
@@ -80,16 +109,19 @@ fn get_init_resource() -> (WaveConfig, WaveGrid) {
 
     wave_grid.update_grid_item_value(GridPosition::new(random_x, random_y), random_value);
     println!("init:{:?}\nx:{:?},y:{:?}", random_value, random_x, random_y);
-    (WaveConfig(config), wave_grid)
+    Ok((WaveConfig(config), wave_grid))
 }
 
-fn get_config() -> (ConfigMap, HashSet<u32>) {
-    let json_str = fs::read_to_string("assets/json/wave_function_collapse_map.json").ok().unwrap();
-    let config = serde_json::from_str::<ConfigMap>(&json_str).ok().unwrap();
-    (
+fn get_config() -> Result<(ConfigMap, HashSet<u32>), String> {
+    let json_str = fs::read_to_string(CONFIG_PATH).map_err(|err| err.to_string())?;
+    let config = serde_json::from_str::<ConfigMap>(&json_str).map_err(|err| err.to_string())?;
+    if config.is_empty() {
+        return Err("no tiles configured".to_string());
+    }
+    Ok((
         config.clone(),
         config.iter().map(|(&code, _)| code).collect::<HashSet<u32>>(),
-    )
+    ))
 }
 
 #[cfg(test)]
@@ -99,7 +131,7 @@ mod tests {
       #[test]
       fn it_works() {
             let a = GridItem::new(GridPosition::new(1, 1), HashSet::from([9,10,13,14]));
-            println!("{:?}",a.get_beside_impossible_value(&get_init_resource().0.0));
+            println!("{:?}",a.get_beside_impossible_value(&get_init_resource().unwrap().0.0));
             // println!("{:#?}", get_init_resource());
       }
 }
\ No newline at end of file